@@ -51,17 +51,55 @@ function renderNode(node: Spanned<WikitextSimplifiedNode>, key: string | number)
     case 'link':
       return (
         <a key={key} href={`https://en.wikipedia.org/wiki/${encodeURIComponent(v.title)}`} className="text-cyan-400 hover:text-cyan-300 underline" target="_blank" rel="noopener noreferrer">
-          {v.text}
+          {v.text.map((child, i) => renderNode(child, `${key}-${i}`))}
         </a>
       );
 
     case 'ext-link':
       return (
         <a key={key} href={v.link} className="text-cyan-400 hover:text-cyan-300 underline" target="_blank" rel="noopener noreferrer">
-          {v.text || v.link}
+          {v.text ? v.text.map((child, i) => renderNode(child, `${key}-${i}`)) : v.link}
         </a>
       );
 
+    case 'image':
+      return (
+        <figure key={key} className="inline-block border border-slate-600 rounded p-1 my-2 text-sm">
+          <div className="text-purple-300">{v.file}</div>
+          {v.caption.length > 0 && (
+            <figcaption className="text-slate-400">
+              {v.caption.map((child, i) => renderNode(child, `${key}-caption-${i}`))}
+            </figcaption>
+          )}
+        </figure>
+      );
+
+    case 'gallery':
+      return (
+        <div key={key} className="flex flex-wrap gap-2 my-2">
+          {v.entries.map((entry, i) => (
+            <figure key={i} className="inline-block border border-slate-600 rounded p-1 text-sm">
+              <div className="text-purple-300">{entry.file}</div>
+              {entry.caption.length > 0 && (
+                <figcaption className="text-slate-400">
+                  {entry.caption.map((child, j) => renderNode(child, `${key}-${i}-caption-${j}`))}
+                </figcaption>
+              )}
+            </figure>
+          ))}
+        </div>
+      );
+
+    case 'behavior-switch':
+      return null;
+
+    case 'category':
+      return (
+        <span key={key} className="text-amber-300 text-xs px-1 rounded border border-amber-700">
+          Category: {v.name}
+        </span>
+      );
+
     case 'template':
       return (
         <span key={key} className="bg-emerald-900/50 text-emerald-300 px-1 rounded border border-emerald-700">
@@ -73,7 +111,7 @@ function renderNode(node: Spanned<WikitextSimplifiedNode>, key: string | number)
               {v.parameters.map((p, i) => (
                 <span key={i}>
                   {i > 0 && '|'}
-                  {p.name}={p.value}
+                  {p.name}={p.raw_value}
                 </span>
               ))}
             </>
@@ -234,6 +272,27 @@ function renderNode(node: Spanned<WikitextSimplifiedNode>, key: string | number)
     case 'newline':
       return <br key={key} />;
 
+    case 'reference':
+      return (
+        <sup key={key} className="text-cyan-400" title={v.name ?? undefined}>
+          [{v.group ? `${v.group} ` : ''}{v.name ?? 'ref'}]
+        </sup>
+      );
+
+    case 'reference-list':
+      return (
+        <div key={key} className="text-slate-400 text-sm border-t border-slate-600 my-2 pt-1">
+          References{v.group ? ` (${v.group})` : ''}
+          {v.children.map((child, i) => renderNode(child, `${key}-${i}`))}
+        </div>
+      );
+
+    case 'nowiki':
+      return <span key={key}>{v.text}</span>;
+
+    case 'comment':
+      return null;
+
     default:
       return <span key={key} className="text-red-400">[Unknown node type]</span>;
   }
@@ -1,5 +1,5 @@
 import { useState, useCallback, type ReactNode } from 'react';
-import type { Spanned, WikitextSimplifiedNode, TemplateParameter, WikitextSimplifiedListItem, WikitextSimplifiedDefinitionListItem, WikitextSimplifiedTableRow, WikitextSimplifiedTableCaption } from './wasm/wikitext_wasm';
+import type { Spanned, WikitextSimplifiedNode, TemplateParameter, WikitextSimplifiedListItem, WikitextSimplifiedDefinitionListItem, WikitextSimplifiedTableRow, WikitextSimplifiedTableCaption, WikitextSimplifiedGalleryEntry } from './wasm/wikitext_wasm';
 
 // Color scheme for different node types (blue and green theme)
 const nodeColors: Record<string, string> = {
@@ -9,6 +9,10 @@ const nodeColors: Record<string, string> = {
   'heading': 'text-blue-400',
   'link': 'text-cyan-400',
   'ext-link': 'text-cyan-300',
+  'image': 'text-purple-300',
+  'gallery': 'text-purple-400',
+  'behavior-switch': 'text-amber-500',
+  'category': 'text-amber-300',
   'bold': 'text-blue-300',
   'italic': 'text-blue-200',
   'blockquote': 'text-teal-400',
@@ -17,6 +21,10 @@ const nodeColors: Record<string, string> = {
   'small': 'text-green-200',
   'preformatted': 'text-teal-300',
   'tag': 'text-emerald-500',
+  'reference': 'text-teal-300',
+  'reference-list': 'text-teal-400',
+  'nowiki': 'text-green-300',
+  'comment': 'text-slate-500',
   'text': 'text-green-400',
   'table': 'text-blue-500',
   'ordered-list': 'text-cyan-500',
@@ -46,6 +54,10 @@ function TreeNode({ node, depth, onNodeHover, onNodeClick }: TreeNodeProps) {
     if ('items' in v && Array.isArray(v.items)) return v.items.length > 0;
     if ('rows' in v && Array.isArray(v.rows)) return v.rows.length > 0;
     if ('default' in v && v.default) return v.default.length > 0;
+    if ('caption' in v && Array.isArray(v.caption)) return v.caption.length > 0;
+    if (v.type === 'link') return v.text.length > 0;
+    if (v.type === 'ext-link') return (v.text?.length ?? 0) > 0;
+    if ('entries' in v && Array.isArray(v.entries)) return v.entries.length > 0;
     if ('parameters' in v && Array.isArray(v.parameters)) return v.parameters.length > 0;
     return false;
   };
@@ -55,10 +67,26 @@ function TreeNode({ node, depth, onNodeHover, onNodeClick }: TreeNodeProps) {
     switch (v.type) {
       case 'text':
         return `text: "${v.text.length > 30 ? v.text.slice(0, 30) + '...' : v.text}"`;
+      case 'reference':
+        return `reference${v.name ? ': ' + v.name : ''}${v.group ? ' (' + v.group + ')' : ''}`;
+      case 'reference-list':
+        return `reference-list${v.group ? ' (' + v.group + ')' : ''}`;
+      case 'nowiki':
+        return `nowiki: "${v.text.length > 30 ? v.text.slice(0, 30) + '...' : v.text}"`;
+      case 'comment':
+        return `comment: <!--${v.text.length > 30 ? v.text.slice(0, 30) + '...' : v.text}-->`;
       case 'link':
-        return `link: [[${v.title}${v.text !== v.title ? '|' + v.text : ''}]]`;
+        return `link: [[${v.title}]]`;
       case 'ext-link':
-        return `ext-link: [${v.link}${v.text ? ' ' + v.text : ''}]`;
+        return `ext-link: ${v.bracketed ? `[${v.link}]` : v.link}`;
+      case 'image':
+        return `image: [[${v.file}]]`;
+      case 'gallery':
+        return `gallery (${v.entries.length} images)`;
+      case 'behavior-switch':
+        return `behavior-switch: __${v.name}__`;
+      case 'category':
+        return `category: ${v.name}${v.sort_key ? ' (' + v.sort_key + ')' : ''}`;
       case 'template':
         return `template: {{${v.name}}}`;
       case 'template-parameter-use':
@@ -66,7 +94,7 @@ function TreeNode({ node, depth, onNodeHover, onNodeClick }: TreeNodeProps) {
       case 'heading':
         return `heading (h${v.level})`;
       case 'tag':
-        return `tag: <${v.name}${v.attributes ? ' ' + v.attributes : ''}>`;
+        return `tag: <${v.name}${v.raw_attributes ? ' ' + v.raw_attributes : ''}>`;
       case 'redirect':
         return `redirect: [[${v.target}]]`;
       default:
@@ -109,13 +137,70 @@ function TreeNode({ node, depth, onNodeHover, onNodeClick }: TreeNodeProps) {
       );
     }
 
+    if ('caption' in v && Array.isArray(v.caption) && v.caption.length > 0) {
+      children.push(
+        <div key="caption" className="ml-4">
+          <span className="text-slate-500 text-xs">caption:</span>
+          {v.caption.map((child, i) => (
+            <TreeNode
+              key={`caption-${i}`}
+              node={child}
+              depth={depth + 1}
+              onNodeHover={onNodeHover}
+              onNodeClick={onNodeClick}
+            />
+          ))}
+        </div>
+      );
+    }
+
+    const linkText = v.type === 'link' || v.type === 'ext-link' ? v.text ?? [] : [];
+    if (linkText.length > 0) {
+      children.push(
+        <div key="text" className="ml-4">
+          <span className="text-slate-500 text-xs">text:</span>
+          {linkText.map((child, i) => (
+            <TreeNode
+              key={`text-${i}`}
+              node={child}
+              depth={depth + 1}
+              onNodeHover={onNodeHover}
+              onNodeClick={onNodeClick}
+            />
+          ))}
+        </div>
+      );
+    }
+
+    if ('entries' in v && Array.isArray(v.entries) && v.entries.length > 0) {
+      children.push(
+        <div key="entries" className="ml-4">
+          <span className="text-slate-500 text-xs">entries:</span>
+          {(v.entries as WikitextSimplifiedGalleryEntry[]).map((entry, i) => (
+            <div key={`entry-${i}`} className="ml-4">
+              <span className="text-purple-300 text-sm">{entry.file}</span>
+              {entry.caption.map((child, j) => (
+                <TreeNode
+                  key={`entry-${i}-${j}`}
+                  node={child}
+                  depth={depth + 1}
+                  onNodeHover={onNodeHover}
+                  onNodeClick={onNodeClick}
+                />
+              ))}
+            </div>
+          ))}
+        </div>
+      );
+    }
+
     if ('parameters' in v && Array.isArray(v.parameters) && v.parameters.length > 0) {
       children.push(
         <div key="params" className="ml-4">
           <span className="text-slate-500 text-xs">parameters:</span>
           {(v.parameters as TemplateParameter[]).map((param, i) => (
             <div key={`param-${i}`} className="ml-4 text-emerald-200 text-sm">
-              {param.name}={param.value.length > 20 ? param.value.slice(0, 20) + '...' : param.value}
+              {param.name}={param.raw_value.length > 20 ? param.raw_value.slice(0, 20) + '...' : param.raw_value}
             </div>
           ))}
         </div>
@@ -0,0 +1,235 @@
+//! Parsing of the HTML attributes of tags, e.g. `<div class="foo" style="color: red">`.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::{Span, WikitextSimplifiedNode};
+
+/// An HTML attribute of a tag, e.g. `class="foo"`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TagAttribute {
+    /// The name of the attribute, in lowercase
+    pub name: String,
+    /// The value of the attribute, with whitespace collapsed and character references
+    /// decoded, or `None` if the attribute has no value (e.g. `<div hidden>`)
+    pub value: Option<String>,
+    /// How the value of the attribute was quoted
+    pub quote: AttributeQuote,
+    /// The span of the attribute in the wikitext, from the start of its name to the end of
+    /// its value
+    pub span: Span,
+}
+
+/// How the value of a [`TagAttribute`] was quoted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "kebab-case")]
+pub enum AttributeQuote {
+    /// The value was in double quotes, e.g. `class="foo"`
+    Double,
+    /// The value was in single quotes, e.g. `class='foo'`
+    Single,
+    /// The value was not quoted, or the attribute has no value, e.g. `class=foo`
+    Unquoted,
+}
+
+impl WikitextSimplifiedNode {
+    /// Returns the HTML attribute of this tag or gallery with the given name, if any.
+    ///
+    /// If the attribute is specified more than once, the last one is used, as MediaWiki does.
+    pub fn attribute(&self, name: &str) -> Option<&TagAttribute> {
+        match self {
+            Self::Tag { attributes, .. } | Self::Gallery { attributes, .. } => {
+                find_tag_attribute(attributes, name)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Finds the last attribute with the given name.
+pub(crate) fn find_tag_attribute<'a>(
+    attributes: &'a [TagAttribute],
+    name: &str,
+) -> Option<&'a TagAttribute> {
+    attributes
+        .iter()
+        .rev()
+        .find(|attribute| attribute.name.eq_ignore_ascii_case(name))
+}
+
+/// Parses the attributes of a tag as MediaWiki does, given the text between the tag name
+/// and the closing `>` and where that text starts in the wikitext.
+///
+/// Text that does not form a valid attribute is ignored, and attributes are returned in
+/// the order they were written, including any duplicates.
+pub(crate) fn parse_tag_attributes(text: &str, start: usize) -> Vec<TagAttribute> {
+    // The slash of a self-closing tag is not part of the attributes
+    let text = text.strip_suffix('/').unwrap_or(text);
+    let is_space = |c: char| matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ');
+
+    let mut attributes = vec![];
+    let mut index = 0;
+    while index < text.len() {
+        let c = text[index..].chars().next().unwrap();
+        let preceded_by_space = index == 0 || text[..index].ends_with(is_space);
+        if preceded_by_space && is_attribute_name_start(c) {
+            if let Some((attribute, end)) = parse_tag_attribute(text, index, start) {
+                attributes.push(attribute);
+                index = end;
+                continue;
+            }
+        }
+        index += c.len_utf8();
+    }
+    attributes
+}
+
+/// Parses the attribute whose name starts at `index`, returning it and the index after it
+/// if it is followed by whitespace or the end of the text.
+fn parse_tag_attribute(text: &str, index: usize, start: usize) -> Option<(TagAttribute, usize)> {
+    let is_space = |c: char| matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ');
+    let is_boundary = |end: usize| text[end..].chars().next().is_none_or(is_space);
+
+    let name_end = text[index..]
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| !is_attribute_name_character(*c))
+        .map_or(text.len(), |(offset, _)| index + offset);
+    let attribute = |value: Option<&str>, quote: AttributeQuote, end: usize| TagAttribute {
+        name: text[index..name_end].to_lowercase(),
+        value: value.map(normalize_attribute_value),
+        quote,
+        span: Span {
+            start: start + index,
+            end: start + end,
+        },
+    };
+
+    let after_name = text[name_end..].trim_start_matches(is_space);
+    let Some(value_start) = after_name.strip_prefix('=') else {
+        return is_boundary(name_end).then(|| {
+            (
+                attribute(None, AttributeQuote::Unquoted, name_end),
+                name_end,
+            )
+        });
+    };
+    let value_start = value_start.trim_start_matches(is_space);
+    let value_index = text.len() - value_start.len();
+
+    // Quoted values without a closing quote run to the end of the tag
+    for (quote_character, quote) in [
+        ('"', AttributeQuote::Double),
+        ('\'', AttributeQuote::Single),
+    ] {
+        let Some(quoted) = value_start.strip_prefix(quote_character) else {
+            continue;
+        };
+        let (value, end) = match quoted.find(quote_character) {
+            Some(value_end) => (&quoted[..value_end], value_index + value_end + 2),
+            None => (quoted, text.len()),
+        };
+        if is_boundary(end) {
+            return Some((attribute(Some(value), quote, end), end));
+        }
+    }
+
+    // Otherwise, the value runs until the next whitespace, including any quotes
+    let end = value_start
+        .find(|c: char| is_space(c) || c == '>')
+        .map_or(text.len(), |value_end| value_index + value_end);
+    is_boundary(end).then(|| {
+        (
+            attribute(Some(&text[value_index..end]), AttributeQuote::Unquoted, end),
+            end,
+        )
+    })
+}
+
+fn is_attribute_name_start(c: char) -> bool {
+    c == ':' || c == '_' || c.is_alphanumeric()
+}
+
+fn is_attribute_name_character(c: char) -> bool {
+    is_attribute_name_start(c) || c == '-' || c == '.'
+}
+
+/// Collapses whitespace and decodes character references in an attribute value.
+fn normalize_attribute_value(value: &str) -> String {
+    let value = value
+        .split(['\t', '\r', '\n', ' '])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    decode_character_references(&value)
+}
+
+/// Decodes numeric character references and the named references commonly used in
+/// attributes. Anything that is not a valid reference is kept as written.
+fn decode_character_references(text: &str) -> String {
+    const NAMED_REFERENCES: &[(&str, char)] = &[
+        ("amp", '&'),
+        ("lt", '<'),
+        ("gt", '>'),
+        ("quot", '"'),
+        ("apos", '\''),
+        ("nbsp", '\u{A0}'),
+        ("ndash", '–'),
+        ("mdash", '—'),
+        ("middot", '·'),
+        ("times", '×'),
+        ("deg", '°'),
+        ("copy", '©'),
+        ("reg", '®'),
+    ];
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(ampersand) = rest.find('&') {
+        output.push_str(&rest[..ampersand]);
+        rest = &rest[ampersand..];
+        let decoded = rest[1..].split_once(';').and_then(|(reference, _)| {
+            let character = if let Some(hex) = reference
+                .strip_prefix("#x")
+                .or_else(|| reference.strip_prefix("#X"))
+            {
+                hex.chars()
+                    .all(|c| c.is_ascii_hexdigit())
+                    .then(|| u32::from_str_radix(hex, 16).ok())
+                    .flatten()
+                    .and_then(char::from_u32)
+            } else if let Some(decimal) = reference.strip_prefix('#') {
+                decimal
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then(|| decimal.parse().ok())
+                    .flatten()
+                    .and_then(char::from_u32)
+            } else {
+                NAMED_REFERENCES
+                    .iter()
+                    .find(|(name, _)| *name == reference)
+                    .map(|(_, character)| *character)
+            };
+            Some((character?, reference.len() + 2))
+        });
+        match decoded {
+            Some((character, length)) => {
+                output.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
@@ -0,0 +1,120 @@
+//! Grouping of the items of [`crate::WikitextSimplifiedNode::DefinitionList`] nodes into
+//! terms and their details.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::{
+    DefinitionListItemType, Span, Spanned, WikitextSimplifiedDefinitionListItem,
+    WikitextSimplifiedNode,
+};
+
+/// A term of a definition list and the details that follow it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DefinitionListEntry {
+    /// The content of the term (`;`), or `None` if the details were not preceded by a term.
+    ///
+    /// Details without a term are most often lines indented with `:`, as on talk pages,
+    /// rather than actual definitions.
+    pub term: Option<Vec<Spanned<WikitextSimplifiedNode>>>,
+    /// The content of each of the details (`:`) of the term, in order
+    pub details: Vec<Vec<Spanned<WikitextSimplifiedNode>>>,
+}
+impl DefinitionListEntry {
+    /// Returns whether these are details without a term, i.e. lines indented with `:`.
+    pub fn is_indentation(&self) -> bool {
+        self.term.is_none()
+    }
+}
+
+impl WikitextSimplifiedNode {
+    /// Groups the items of this node into terms and their details if it is a definition
+    /// list.
+    ///
+    /// A term and details on the same line (e.g. `;term:details`) are split at the first
+    /// colon outside of any other markup if the parser did not already split them, and
+    /// consecutive details without a term are grouped into a single entry without a term.
+    pub fn definition_list_entries(&self) -> Option<Vec<DefinitionListEntry>> {
+        let Self::DefinitionList { items } = self else {
+            return None;
+        };
+
+        let mut entries: Vec<DefinitionListEntry> = vec![];
+        for WikitextSimplifiedDefinitionListItem { type_, content, .. } in items {
+            match type_ {
+                DefinitionListItemType::Term => {
+                    let (term, details) = split_inline_details(content);
+                    entries.push(DefinitionListEntry {
+                        term: Some(term),
+                        details: details.into_iter().collect(),
+                    });
+                }
+                DefinitionListItemType::Details => match entries.last_mut() {
+                    Some(entry) => entry.details.push(content.clone()),
+                    None => entries.push(DefinitionListEntry {
+                        term: None,
+                        details: vec![content.clone()],
+                    }),
+                },
+            }
+        }
+        Some(entries)
+    }
+}
+
+/// Splits the content of a term at its first colon outside of any other markup, returning
+/// the term and the details following it on the same line, if any.
+fn split_inline_details(
+    content: &[Spanned<WikitextSimplifiedNode>],
+) -> (
+    Vec<Spanned<WikitextSimplifiedNode>>,
+    Option<Vec<Spanned<WikitextSimplifiedNode>>>,
+) {
+    let colon = content
+        .iter()
+        .enumerate()
+        .find_map(|(index, node)| match &node.value {
+            WikitextSimplifiedNode::Text { text } => Some((index, node, text, text.find(':')?)),
+            _ => None,
+        });
+    let Some((index, node, text, colon)) = colon else {
+        return (content.to_vec(), None);
+    };
+
+    // Spans can only be split if the text matches the source
+    let offset_span = |start: usize, end: usize| {
+        if text.len() == node.span.end - node.span.start {
+            Span {
+                start: node.span.start + start,
+                end: node.span.start + end,
+            }
+        } else {
+            node.span
+        }
+    };
+    let text_node = |start: usize, end: usize| Spanned {
+        value: WikitextSimplifiedNode::Text {
+            text: text[start..end].to_string(),
+        },
+        span: offset_span(start, end),
+    };
+
+    let mut term = content[..index].to_vec();
+    let term_end = text[..colon].trim_end().len();
+    if term_end > 0 {
+        term.push(text_node(0, term_end));
+    }
+
+    let mut details = vec![];
+    let details_start = text.len() - text[colon + 1..].trim_start().len();
+    if details_start < text.len() {
+        details.push(text_node(details_start, text.len()));
+    }
+    details.extend_from_slice(&content[index + 1..]);
+
+    (term, Some(details))
+}
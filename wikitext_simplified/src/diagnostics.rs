@@ -0,0 +1,112 @@
+//! Diagnostics that can be reported while simplifying wikitext.
+//!
+//! Unlike [`crate::SimplificationError`], diagnostics do not stop simplification; they
+//! describe places where the simplified AST may not match what the author intended.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::Span;
+
+/// A problem found in the wikitext while simplifying it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub severity: DiagnosticSeverity,
+    /// The kind of problem
+    pub kind: DiagnosticKind,
+    /// The span of the source text that the problem refers to
+    pub span: Span,
+    /// A human-readable description of the problem
+    pub message: String,
+}
+
+/// The severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum DiagnosticSeverity {
+    /// The wikitext is valid, but likely does not do what the author intended
+    Warning,
+    /// The wikitext is invalid, and was simplified on a best-effort basis
+    Error,
+}
+
+/// The kind of problem reported by a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum DiagnosticKind {
+    /// A template parameter was overridden by a later parameter with the same name
+    DuplicateTemplateParameter,
+    /// A template parameter value could not be simplified, and was kept as text
+    UnsimplifiableTemplateParameter,
+    /// A tag was not closed, and was closed implicitly
+    UnclosedTag,
+    /// Bold or italic formatting was not closed, and was closed implicitly
+    UnclosedFormatting,
+    /// A closing tag did not match any open tag, and was discarded
+    UnmatchedClosingTag,
+    /// Formatting was still open when the formatting or element it was opened within was
+    /// closed, and was closed and reopened after it to match, as browsers do
+    MisnestedFormatting,
+    /// A line had an odd number of both bold and italic apostrophes, and a bold was treated
+    /// as an apostrophe followed by italics
+    UnbalancedQuotes,
+    /// A node that could not be simplified was kept as text
+    UnknownNode,
+    /// The wikitext could not be parsed, and was kept as text
+    UnparsableWikitext,
+}
+impl DiagnosticKind {
+    /// Returns a stable identifier for this kind of problem, suitable for filtering and
+    /// aggregating diagnostics, e.g. `unclosed-tag`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::DuplicateTemplateParameter => "duplicate-template-parameter",
+            Self::UnsimplifiableTemplateParameter => "unsimplifiable-template-parameter",
+            Self::UnclosedTag => "unclosed-tag",
+            Self::UnclosedFormatting => "unclosed-formatting",
+            Self::UnmatchedClosingTag => "unmatched-closing-tag",
+            Self::MisnestedFormatting => "misnested-formatting",
+            Self::UnbalancedQuotes => "unbalanced-quotes",
+            Self::UnknownNode => "unknown-node",
+            Self::UnparsableWikitext => "unparsable-wikitext",
+        }
+    }
+}
+
+/// Collects the [`Diagnostic`]s reported during simplification, and tracks whether
+/// malformed wikitext should be recovered from rather than reported as an error
+#[derive(Debug, Default)]
+pub(crate) struct DiagnosticCollector {
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    pub(crate) recover: bool,
+}
+impl DiagnosticCollector {
+    /// Creates a collector that recovers from malformed wikitext.
+    pub(crate) fn recovering() -> Self {
+        Self {
+            diagnostics: vec![],
+            recover: true,
+        }
+    }
+
+    /// Reports a diagnostic.
+    pub(crate) fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Runs `f` without recovering from malformed wikitext, so that its caller can handle
+    /// any errors itself.
+    pub(crate) fn without_recovery<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let recover = std::mem::replace(&mut self.recover, false);
+        let result = f(self);
+        self.recover = recover;
+        result
+    }
+}
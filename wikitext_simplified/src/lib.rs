@@ -11,14 +11,49 @@ pub use wikitext_util;
 
 use parse_wiki_text_2 as pwt;
 
+mod diagnostics;
+pub use diagnostics::{Diagnostic, DiagnosticKind, DiagnosticSeverity};
+
 mod simplification;
 pub use simplification::{
-    simplify_wikitext_node, simplify_wikitext_nodes, DefinitionListItemType, NodeStructureError,
-    SimplificationError, SimplificationErrorContext, Span, Spanned, TemplateParameter,
-    WikitextSimplifiedDefinitionListItem, WikitextSimplifiedNode, WikitextSimplifiedTableCaption,
-    WikitextSimplifiedTableCell, WikitextSimplifiedTableRow,
+    simplify_wikitext_node, simplify_wikitext_node_with_config, simplify_wikitext_nodes,
+    simplify_wikitext_nodes_recovering, simplify_wikitext_nodes_with_config,
+    simplify_wikitext_nodes_with_diagnostics, DefinitionListItemType, ImageAlignment, ImageFormat,
+    ImageOptions, ImageSize, ImageVerticalAlignment, NodeStructureError, SimplificationConfig,
+    SimplificationError, SimplificationErrorContext, Span, Spanned, TemplateParameter, VisitConfig,
+    WikitextSimplifiedDefinitionListItem, WikitextSimplifiedGalleryEntry, WikitextSimplifiedNode,
+    WikitextSimplifiedTableCaption, WikitextSimplifiedTableCell, WikitextSimplifiedTableRow,
 };
 
+mod link;
+pub use link::LinkTarget;
+
+mod attributes;
+pub use attributes::{AttributeQuote, TagAttribute};
+
+mod definition_list;
+pub use definition_list::DefinitionListEntry;
+
+mod table;
+pub use table::{TableGrid, TableGridCell};
+
+mod table_export;
+pub use table_export::{TableCellContent, TableExportOptions, TableRecord, TableValue};
+
+mod page;
+pub use page::{collect_behavior_switches, collect_categories, PageCategory};
+
+mod quotes;
+
+mod section;
+pub use section::{collect_sections, find_section, get_section, replace_section, Section};
+
+mod template;
+pub use template::{TemplateModifier, TemplateName, TemplateRef};
+
+mod references;
+pub use references::{collect_footnotes, Footnote, FootnoteList};
+
 #[cfg(test)]
 mod tests;
 
@@ -44,17 +79,92 @@ impl std::error::Error for ParseAndSimplifyWikitextError<'_> {}
 
 /// Helper function that parses wikitext and converts it into a simplified AST structure.
 ///
+/// Calls [`parse_and_simplify_wikitext_with_config`] with the default configuration.
+///
 /// # Errors
 ///
 /// This function will return an error if the wikitext cannot be parsed or simplified.
 pub fn parse_and_simplify_wikitext<'a>(
     wikitext: &'a str,
     pwt_configuration: &pwt::Configuration,
+) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, ParseAndSimplifyWikitextError<'a>> {
+    parse_and_simplify_wikitext_with_config(
+        wikitext,
+        pwt_configuration,
+        &SimplificationConfig::default(),
+    )
+}
+
+/// Helper function that parses wikitext and converts it into a simplified AST structure,
+/// using the given simplification configuration.
+///
+/// # Errors
+///
+/// This function will return an error if the wikitext cannot be parsed or simplified.
+pub fn parse_and_simplify_wikitext_with_config<'a>(
+    wikitext: &'a str,
+    pwt_configuration: &pwt::Configuration,
+    config: &SimplificationConfig,
 ) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, ParseAndSimplifyWikitextError<'a>> {
     let output = pwt_configuration
         .parse(wikitext)
         .map_err(ParseAndSimplifyWikitextError::ParseError)?;
 
-    simplify_wikitext_nodes(wikitext, &output.nodes)
+    simplify_wikitext_nodes_with_config(wikitext, &output.nodes, config)
         .map_err(ParseAndSimplifyWikitextError::SimplificationError)
 }
+
+/// Helper function that parses wikitext and converts it into a simplified AST structure,
+/// returning any [`Diagnostic`]s reported during simplification.
+///
+/// # Errors
+///
+/// This function will return an error if the wikitext cannot be parsed or simplified.
+pub fn parse_and_simplify_wikitext_with_diagnostics<'a>(
+    wikitext: &'a str,
+    pwt_configuration: &pwt::Configuration,
+    config: &SimplificationConfig,
+) -> Result<
+    (Vec<Spanned<WikitextSimplifiedNode>>, Vec<Diagnostic>),
+    ParseAndSimplifyWikitextError<'a>,
+> {
+    let output = pwt_configuration
+        .parse(wikitext)
+        .map_err(ParseAndSimplifyWikitextError::ParseError)?;
+
+    simplify_wikitext_nodes_with_diagnostics(wikitext, &output.nodes, config)
+        .map_err(ParseAndSimplifyWikitextError::SimplificationError)
+}
+
+/// Helper function that parses wikitext and converts it into a simplified AST structure,
+/// recovering from malformed wikitext instead of returning an error.
+///
+/// See [`simplify_wikitext_nodes_recovering`] for how malformed wikitext is handled. If the
+/// wikitext cannot be parsed at all, it is kept as a single text node.
+pub fn parse_and_simplify_wikitext_recovering(
+    wikitext: &str,
+    pwt_configuration: &pwt::Configuration,
+    config: &SimplificationConfig,
+) -> (Vec<Spanned<WikitextSimplifiedNode>>, Vec<Diagnostic>) {
+    let Ok(output) = pwt_configuration.parse(wikitext) else {
+        let span = Span {
+            start: 0,
+            end: wikitext.len(),
+        };
+        let diagnostic = Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            kind: DiagnosticKind::UnparsableWikitext,
+            span,
+            message: "Wikitext could not be parsed, and was kept as text".into(),
+        };
+        let text = Spanned {
+            value: WikitextSimplifiedNode::Text {
+                text: wikitext.to_string(),
+            },
+            span,
+        };
+        return (vec![text], vec![diagnostic]);
+    };
+
+    simplify_wikitext_nodes_recovering(wikitext, &output.nodes, config)
+}
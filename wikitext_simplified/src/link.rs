@@ -0,0 +1,108 @@
+//! Resolution of the targets of [`crate::WikitextSimplifiedNode::Link`] nodes.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use wikitext_util::{normalize_page_title, wikipedia_link_prefixes, LinkPrefixes};
+
+/// The target of an internal link, resolved from the target as written
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct LinkTarget {
+    /// Whether the target started with a colon (e.g. `[[:Category:Foo]]`), which makes
+    /// category, file and interlanguage links display inline instead
+    pub leading_colon: bool,
+    /// The interwiki or interlanguage prefix, in lowercase (e.g. `fr` for `[[:fr:Paris]]`)
+    pub interwiki: Option<String>,
+    /// Whether this is an interlanguage link (e.g. `[[fr:Paris]]`), which links to the same
+    /// page in another language and is shown in the sidebar rather than inline
+    pub interlanguage: bool,
+    /// The canonical name of the namespace of the page, or empty for the main namespace.
+    ///
+    /// Namespaces are not resolved for interwiki links, as they depend on the other wiki.
+    pub namespace: String,
+    /// The title of the page, without its namespace.
+    ///
+    /// This is normalized for local pages, but kept as written for interwiki links, and is
+    /// empty for links to a section of the current page (e.g. `[[#History]]`).
+    pub title: String,
+    /// The section of the page being linked to, if any
+    pub fragment: Option<String>,
+}
+impl LinkTarget {
+    /// Resolves the target of a link as MediaWiki does.
+    ///
+    /// Helper function that calls [`LinkTarget::parse_with_prefixes`] with the prefixes used by
+    /// Wikipedia.
+    pub fn parse(target: &str) -> Self {
+        Self::parse_with_prefixes(target, wikipedia_link_prefixes())
+    }
+
+    /// Resolves the target of a link as MediaWiki does, using the given prefixes to
+    /// recognise namespaces and other wikis.
+    pub fn parse_with_prefixes(target: &str, prefixes: &LinkPrefixes) -> Self {
+        let target = target.trim();
+        let (leading_colon, target) = match target.strip_prefix(':') {
+            Some(target) => (true, target.trim_start()),
+            None => (false, target),
+        };
+
+        let (page, fragment) = match target.split_once('#') {
+            Some((page, fragment)) => (page, Some(fragment.trim().to_string())),
+            None => (target, None),
+        };
+
+        // Namespaces take precedence over interwiki prefixes with the same name
+        let interwiki = page.split_once(':').filter(|(prefix, _)| {
+            prefixes.namespace(prefix).is_none() && prefixes.is_interwiki(prefix)
+        });
+        if let Some((prefix, page)) = interwiki {
+            return Self {
+                leading_colon,
+                interwiki: Some(prefix.trim().to_lowercase()),
+                interlanguage: !leading_colon && prefixes.is_language(prefix),
+                namespace: String::new(),
+                title: page.trim().to_string(),
+                fragment,
+            };
+        }
+
+        let (namespace, title) = page
+            .split_once(':')
+            .and_then(|(prefix, title)| Some((prefixes.namespace(prefix)?, title)))
+            .unwrap_or(("", page));
+
+        Self {
+            leading_colon,
+            interwiki: None,
+            interlanguage: false,
+            namespace: namespace.to_string(),
+            title: normalize_page_title(title),
+            fragment,
+        }
+    }
+
+    /// Returns the full title of the page, including its namespace and interwiki prefix
+    /// but not its fragment (e.g. `Help:Contents`).
+    pub fn full_title(&self) -> String {
+        let mut full_title = String::new();
+        if let Some(interwiki) = &self.interwiki {
+            full_title.push_str(interwiki);
+            full_title.push(':');
+        }
+        if !self.namespace.is_empty() {
+            full_title.push_str(&self.namespace);
+            full_title.push(':');
+        }
+        full_title.push_str(&self.title);
+        full_title
+    }
+
+    /// Returns whether this links to a section of the current page (e.g. `[[#History]]`).
+    pub fn is_fragment_only(&self) -> bool {
+        self.interwiki.is_none() && self.namespace.is_empty() && self.title.is_empty()
+    }
+}
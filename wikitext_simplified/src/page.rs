@@ -0,0 +1,62 @@
+//! Helpers for extracting page-level metadata from a simplified AST.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::{Spanned, WikitextSimplifiedNode};
+
+/// A category that a page has been placed in
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct PageCategory {
+    /// The name of the category, without the namespace prefix
+    pub name: String,
+    /// The sort key, if specified
+    pub sort_key: Option<String>,
+}
+
+/// Collects all categories in the given nodes, including those nested within other nodes,
+/// in the order they appear.
+///
+/// This works on any simplified AST, including one produced by template evaluation, in which
+/// case categories added by templates are included as well. Note that the spans of such
+/// categories refer to the source of the template they came from.
+pub fn collect_categories(nodes: &[Spanned<WikitextSimplifiedNode>]) -> Vec<Spanned<PageCategory>> {
+    let mut categories = vec![];
+    for node in nodes {
+        node.visit_spanned(&mut |node| {
+            if let WikitextSimplifiedNode::Category { name, sort_key } = &node.value {
+                categories.push(Spanned {
+                    value: PageCategory {
+                        name: name.clone(),
+                        sort_key: sort_key.clone(),
+                    },
+                    span: node.span,
+                });
+            }
+        });
+    }
+    categories
+}
+
+/// Collects the names of all behavior switches in the given nodes, including those nested
+/// within other nodes.
+///
+/// Names are in uppercase without the surrounding underscores, so `__NOINDEX__` is
+/// returned as `NOINDEX`.
+pub fn collect_behavior_switches(nodes: &[Spanned<WikitextSimplifiedNode>]) -> BTreeSet<String> {
+    let mut switches = BTreeSet::new();
+    for node in nodes {
+        node.value.visit(&mut |node| {
+            if let WikitextSimplifiedNode::BehaviorSwitch { name } = node {
+                switches.insert(name.clone());
+            }
+        });
+    }
+    switches
+}
@@ -0,0 +1,297 @@
+//! Resolution of bold and italic apostrophes into formatting, following MediaWiki's
+//! `doQuotes`.
+//!
+//! `parse_wiki_text_2` only tokenizes runs of apostrophes, so the runs on each line are
+//! resolved here: how many of their apostrophes are text, and which formatting they open
+//! and close. Formatting does not continue past the end of its line.
+
+use parse_wiki_text_2 as pwt;
+use wikitext_util::NodeMetadata;
+
+use crate::{Span, WikitextSimplifiedNode};
+
+/// Bold or italic formatting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quote {
+    Bold,
+    Italic,
+}
+impl Quote {
+    /// Returns an empty node for this formatting.
+    pub(crate) fn node(self) -> WikitextSimplifiedNode {
+        match self {
+            Self::Bold => WikitextSimplifiedNode::Bold { children: vec![] },
+            Self::Italic => WikitextSimplifiedNode::Italic { children: vec![] },
+        }
+    }
+
+    /// Returns whether the node is this formatting.
+    pub(crate) fn matches(self, node: &WikitextSimplifiedNode) -> bool {
+        matches!(
+            (self, node),
+            (Self::Bold, WikitextSimplifiedNode::Bold { .. })
+                | (Self::Italic, WikitextSimplifiedNode::Italic { .. })
+        )
+    }
+}
+
+/// A change to the open formatting made by a run of apostrophes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuoteAction {
+    Open(Quote),
+    Close(Quote),
+}
+
+/// A run of apostrophes, resolved into text and changes to the open formatting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct QuoteRun {
+    /// The span of the whole run in the wikitext
+    pub(crate) span: Span,
+    /// The number of apostrophes at the start of the run that are text
+    pub(crate) literal: usize,
+    /// The changes to the open formatting, in order
+    pub(crate) actions: Vec<QuoteAction>,
+    /// Whether a bold run was treated as an apostrophe followed by italics, because a line
+    /// had an odd number of both bold and italic runs
+    pub(crate) reinterpreted: bool,
+}
+impl QuoteRun {
+    /// Returns whether the run closes formatting only to reopen it, because it was opened
+    /// within the formatting that the run closes (e.g. the last `'''` of `'''a''b'''c''`).
+    pub(crate) fn reopens_formatting(&self) -> bool {
+        self.actions
+            .iter()
+            .enumerate()
+            .any(|(index, action)| match action {
+                QuoteAction::Open(quote) => {
+                    self.actions[..index].contains(&QuoteAction::Close(*quote))
+                }
+                QuoteAction::Close(_) => false,
+            })
+    }
+
+    /// Returns the span of the apostrophes that are markup rather than text.
+    pub(crate) fn markup_span(&self) -> Span {
+        Span {
+            start: self.span.start + self.literal,
+            end: self.span.end,
+        }
+    }
+}
+
+/// The resolved runs of apostrophes in a sequence of nodes, and where its lines end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct QuoteResolution {
+    runs: Vec<QuoteRun>,
+    line_breaks: Vec<usize>,
+    end: usize,
+}
+impl QuoteResolution {
+    /// Resolves the runs of apostrophes among the given nodes.
+    ///
+    /// Only newlines between the nodes, or within their text, end lines; newlines within
+    /// other nodes, such as templates and comments, do not.
+    pub(crate) fn new(wikitext: &str, nodes: &[pwt::Node]) -> Self {
+        let start = nodes
+            .first()
+            .map_or(0, |node| NodeMetadata::for_node(node).start);
+        let mut line_breaks = vec![];
+        let mut run_spans: Vec<Span> = vec![];
+        let mut position = start;
+        for node in nodes {
+            let metadata = NodeMetadata::for_node(node);
+            let newlines_end = match node {
+                pwt::Node::Text { .. } | pwt::Node::ParagraphBreak { .. } => metadata.end,
+                _ => metadata.start,
+            };
+            if position < newlines_end {
+                line_breaks.extend(
+                    wikitext[position..newlines_end]
+                        .match_indices('\n')
+                        .map(|(index, _)| position + index),
+                );
+            }
+            position = position.max(metadata.end);
+
+            if let pwt::Node::Bold { start, .. }
+            | pwt::Node::Italic { start, .. }
+            | pwt::Node::BoldItalic { start, .. } = node
+            {
+                // Later tokens within a run are part of it
+                if run_spans.last().is_some_and(|run| *start < run.end) {
+                    continue;
+                }
+                let length = wikitext[*start..]
+                    .bytes()
+                    .take_while(|&byte| byte == b'\'')
+                    .count();
+                run_spans.push(Span {
+                    start: *start,
+                    end: start + length,
+                });
+            }
+        }
+
+        let mut runs = Vec::with_capacity(run_spans.len());
+        let mut remaining = run_spans.as_slice();
+        while let Some(first) = remaining.first() {
+            let line_start = line_breaks
+                .iter()
+                .rev()
+                .find(|&&line_break| line_break < first.start)
+                .map_or(start, |line_break| line_break + 1);
+            let line_end = line_breaks
+                .iter()
+                .find(|&&line_break| line_break > first.start);
+            let count = remaining
+                .iter()
+                .take_while(|run| line_end.is_none_or(|line_end| run.start < *line_end))
+                .count();
+            runs.extend(resolve_line(wikitext, line_start, &remaining[..count]));
+            remaining = &remaining[count..];
+        }
+
+        Self {
+            runs,
+            line_breaks,
+            end: position,
+        }
+    }
+
+    /// Returns the run of apostrophes that starts at the given position, if any.
+    pub(crate) fn run_at(&self, start: usize) -> Option<&QuoteRun> {
+        self.runs
+            .binary_search_by_key(&start, |run| run.span.start)
+            .ok()
+            .map(|index| &self.runs[index])
+    }
+
+    /// Returns the position of the end of the line containing the given position, which is
+    /// the end of the nodes for the last line.
+    pub(crate) fn line_end(&self, position: usize) -> usize {
+        let index = self
+            .line_breaks
+            .partition_point(|&line_break| line_break < position);
+        self.line_breaks.get(index).copied().unwrap_or(self.end)
+    }
+}
+
+/// Resolves the runs of apostrophes on a line, given where the line starts.
+fn resolve_line(wikitext: &str, line_start: usize, spans: &[Span]) -> Vec<QuoteRun> {
+    // As with MediaWiki, four apostrophes are an apostrophe followed by bold, and more than
+    // five are apostrophes followed by bold italics
+    let mut runs: Vec<QuoteRun> = spans
+        .iter()
+        .map(|span| {
+            let length = span.end - span.start;
+            let literal = match length {
+                4 => 1,
+                6.. => length - 5,
+                _ => 0,
+            };
+            QuoteRun {
+                span: *span,
+                literal,
+                actions: vec![],
+                reinterpreted: false,
+            }
+        })
+        .collect();
+    let markup_length = |run: &QuoteRun| run.span.end - run.span.start - run.literal;
+
+    // If there is an odd number of both bold and italic runs, one of the bold runs was most
+    // likely meant to be an apostrophe followed by italics, such as after a single-letter
+    // word (e.g. `l'''amour''`)
+    let bold_count = runs
+        .iter()
+        .filter(|run| matches!(markup_length(run), 3 | 5))
+        .count();
+    let italic_count = runs
+        .iter()
+        .filter(|run| matches!(markup_length(run), 2 | 5))
+        .count();
+    if bold_count % 2 == 1 && italic_count % 2 == 1 {
+        let mut first_single_letter = None;
+        let mut first_multi_letter = None;
+        let mut first_space = None;
+        for (index, run) in runs.iter().enumerate() {
+            if markup_length(run) != 3 {
+                continue;
+            }
+            let text_start = index
+                .checked_sub(1)
+                .map_or(line_start, |i| runs[i].span.end);
+            let mut before = wikitext[text_start..run.markup_span().start].chars().rev();
+            let (last, second_last) = (before.next(), before.next());
+            if last == Some(' ') {
+                first_space = first_space.or(Some(index));
+            } else if second_last == Some(' ') {
+                first_single_letter = Some(index);
+                break;
+            } else {
+                first_multi_letter = first_multi_letter.or(Some(index));
+            }
+        }
+        if let Some(index) = first_single_letter.or(first_multi_letter).or(first_space) {
+            runs[index].literal += 1;
+            runs[index].reinterpreted = true;
+        }
+    }
+
+    // Track the open formatting, from the outermost to the innermost
+    let mut open: Vec<Quote> = vec![];
+    let lengths: Vec<usize> = runs.iter().map(markup_length).collect();
+    for (index, run) in runs.iter_mut().enumerate() {
+        match lengths[index] {
+            2 => toggle(&mut open, Quote::Italic, &mut run.actions),
+            3 => toggle(&mut open, Quote::Bold, &mut run.actions),
+            _ if open.is_empty() => {
+                // Open both in the order that lets whichever closes first be the inner one
+                let order = match lengths.get(index + 1) {
+                    Some(3 | 5) => [Quote::Italic, Quote::Bold],
+                    _ => [Quote::Bold, Quote::Italic],
+                };
+                for quote in order {
+                    open.push(quote);
+                    run.actions.push(QuoteAction::Open(quote));
+                }
+            }
+            _ => {
+                // Close whatever is open, and open whichever of bold and italics was not
+                let reopen = match open.as_slice() {
+                    [Quote::Bold] => Some(Quote::Italic),
+                    [Quote::Italic] => Some(Quote::Bold),
+                    _ => None,
+                };
+                while let Some(quote) = open.pop() {
+                    run.actions.push(QuoteAction::Close(quote));
+                }
+                if let Some(quote) = reopen {
+                    open.push(quote);
+                    run.actions.push(QuoteAction::Open(quote));
+                }
+            }
+        }
+    }
+    runs
+}
+
+/// Opens the formatting if it is not open, or closes it otherwise, reopening any
+/// formatting opened within it.
+fn toggle(open: &mut Vec<Quote>, quote: Quote, actions: &mut Vec<QuoteAction>) {
+    let Some(index) = open.iter().position(|&q| q == quote) else {
+        open.push(quote);
+        actions.push(QuoteAction::Open(quote));
+        return;
+    };
+    let inner = open.split_off(index + 1);
+    for &q in inner.iter().rev() {
+        actions.push(QuoteAction::Close(q));
+    }
+    open.pop();
+    actions.push(QuoteAction::Close(quote));
+    for &q in &inner {
+        open.push(q);
+        actions.push(QuoteAction::Open(q));
+    }
+}
@@ -0,0 +1,160 @@
+//! Helpers for resolving `<ref>` and `<references>` nodes into numbered footnotes.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::{Span, Spanned, WikitextSimplifiedNode};
+
+/// A single numbered footnote, produced from one or more references with the same name
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Footnote {
+    /// The number of the footnote within its list, starting from 1
+    pub number: usize,
+    /// The name of the footnote, if it was named
+    pub name: Option<String>,
+    /// The content of the footnote, taken from its definition.
+    ///
+    /// This is empty if a named footnote was used but never defined.
+    pub content: Vec<Spanned<WikitextSimplifiedNode>>,
+    /// The spans of each `<ref>` that refers to this footnote, in order of appearance
+    pub uses: Vec<Span>,
+}
+
+/// A list of footnotes for a group, as rendered by a `<references>` tag
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct FootnoteList {
+    /// The group of the footnotes, if any
+    pub group: Option<String>,
+    /// The span of the `<references>` tag that renders this list, or `None` if the
+    /// footnotes were never followed by one and would be listed at the end of the page
+    pub span: Option<Span>,
+    /// The footnotes in the list, in order of their number
+    pub footnotes: Vec<Footnote>,
+}
+
+/// Collects all references in the given nodes into numbered footnote lists, following
+/// the behaviour of MediaWiki's Cite extension:
+///
+/// - each group is numbered separately, in order of the first use of each footnote;
+/// - references with the same name share a number, and take their content from the first
+///   definition with content, which may appear after the use or within a `<references>` tag;
+/// - a `<references>` tag lists the footnotes of its group used so far, after which
+///   numbering for that group starts again;
+/// - footnotes that are not followed by a `<references>` tag are returned in lists at the
+///   end without a span.
+///
+/// List-defined references that are never used are not included.
+pub fn collect_footnotes(nodes: &[Spanned<WikitextSimplifiedNode>]) -> Vec<FootnoteList> {
+    type Key = (Option<String>, String);
+
+    // First, find the content of each named reference, and which references are
+    // list-defined and thus should not be treated as uses
+    let mut definitions: HashMap<Key, Vec<Spanned<WikitextSimplifiedNode>>> = HashMap::new();
+    let mut list_defined: HashSet<*const Spanned<WikitextSimplifiedNode>> = HashSet::new();
+    for node in nodes {
+        node.visit_spanned(&mut |node| match &node.value {
+            WikitextSimplifiedNode::Reference {
+                name: Some(name),
+                group,
+                children,
+            } if !children.is_empty() && !list_defined.contains(&(node as *const _)) => {
+                definitions
+                    .entry((group.clone(), name.clone()))
+                    .or_insert_with(|| children.clone());
+            }
+            WikitextSimplifiedNode::ReferenceList {
+                group: list_group,
+                children,
+            } => {
+                for child in children {
+                    if let WikitextSimplifiedNode::Reference {
+                        name: Some(name),
+                        group,
+                        children,
+                    } = &child.value
+                    {
+                        list_defined.insert(child as *const _);
+                        if !children.is_empty() {
+                            // List-defined references inherit the group of their list
+                            definitions
+                                .entry((group.clone().or(list_group.clone()), name.clone()))
+                                .or_insert_with(|| children.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+
+    // Then, number each use in order, flushing each group when its list is encountered
+    let mut lists = vec![];
+    let mut pending: Vec<(Option<String>, Vec<Footnote>)> = vec![];
+    for node in nodes {
+        node.visit_spanned(&mut |node| match &node.value {
+            WikitextSimplifiedNode::Reference {
+                name,
+                group,
+                children,
+            } if !list_defined.contains(&(node as *const _)) => {
+                let footnotes = match pending.iter_mut().position(|(g, _)| g == group) {
+                    Some(index) => &mut pending[index].1,
+                    None => {
+                        pending.push((group.clone(), vec![]));
+                        &mut pending.last_mut().unwrap().1
+                    }
+                };
+
+                if let Some(footnote) = name
+                    .as_ref()
+                    .and_then(|name| footnotes.iter_mut().find(|f| f.name.as_ref() == Some(name)))
+                {
+                    footnote.uses.push(node.span);
+                    return;
+                }
+
+                let content = match name {
+                    Some(name) if children.is_empty() => definitions
+                        .get(&(group.clone(), name.clone()))
+                        .cloned()
+                        .unwrap_or_default(),
+                    _ => children.clone(),
+                };
+                footnotes.push(Footnote {
+                    number: footnotes.len() + 1,
+                    name: name.clone(),
+                    content,
+                    uses: vec![node.span],
+                });
+            }
+            WikitextSimplifiedNode::ReferenceList { group, .. } => {
+                let footnotes = pending
+                    .iter()
+                    .position(|(g, _)| g == group)
+                    .map(|index| pending.remove(index).1)
+                    .unwrap_or_default();
+                lists.push(FootnoteList {
+                    group: group.clone(),
+                    span: Some(node.span),
+                    footnotes,
+                });
+            }
+            _ => {}
+        });
+    }
+
+    lists.extend(pending.into_iter().map(|(group, footnotes)| FootnoteList {
+        group,
+        span: None,
+        footnotes,
+    }));
+    lists
+}
@@ -0,0 +1,294 @@
+//! Grouping of a page into a tree of sections, following its headings.
+
+use parse_wiki_text_2 as pwt;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use crate::{
+    simplify_wikitext_nodes_recovering, SimplificationConfig, Span, Spanned, WikitextSimplifiedNode,
+};
+
+/// A section of a page, started by a heading and containing everything up to the next
+/// heading of the same or a higher level
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Section {
+    /// The index of the section, as used by MediaWiki's `section=N` parameter: 0 for the
+    /// lead, followed by each heading in the order it appears
+    pub index: usize,
+    /// The level of the section's heading, or 0 for the lead
+    pub level: u8,
+    /// The content of the section's heading, or `None` for the lead
+    pub heading: Option<Vec<Spanned<WikitextSimplifiedNode>>>,
+    /// The content of the section before its first subsection
+    pub children: Vec<Spanned<WikitextSimplifiedNode>>,
+    /// The sections nested within this section
+    pub subsections: Vec<Section>,
+    /// The span of the section in the wikitext, from the start of its heading to the start
+    /// of the next heading of the same or a higher level, including its subsections
+    pub span: Span,
+}
+impl Section {
+    /// Returns the text of the section's heading, or `None` for the lead.
+    pub fn heading_text(&self) -> Option<String> {
+        let heading = self.heading.as_ref()?;
+        let text: String = heading
+            .iter()
+            .map(|node| node.value.to_plain_text())
+            .collect();
+        Some(text.trim().to_string())
+    }
+
+    /// Returns this section or the subsection within it with the given index.
+    pub fn find(&self, index: usize) -> Option<&Section> {
+        if self.index == index {
+            return Some(self);
+        }
+        self.subsections
+            .iter()
+            .find_map(|section| section.find(index))
+    }
+}
+
+/// Groups the given nodes into sections by their headings, returning the lead section
+/// followed by each top-level section.
+///
+/// A section's subsections are the following sections with a lower level heading (i.e.
+/// more `=`s), up to the next heading of the same or a higher level. As with MediaWiki,
+/// headings within HTML tags and tables start sections too, but those within templates
+/// and extension tags do not. A node belongs to the section that is open where it starts,
+/// so a node containing headings is part of the section before them.
+///
+/// The wikitext is used to determine the end of the last section, and should be the
+/// wikitext the nodes were parsed from.
+pub fn collect_sections(wikitext: &str, nodes: &[Spanned<WikitextSimplifiedNode>]) -> Vec<Section> {
+    let end = wikitext.len();
+    let mut lead = Section {
+        index: 0,
+        level: 0,
+        heading: None,
+        children: vec![],
+        subsections: vec![],
+        span: Span { start: 0, end },
+    };
+    let mut sections: Vec<Section> = vec![];
+    // The sections that have not yet ended, from the outermost to the innermost
+    let mut open: Vec<Section> = vec![];
+
+    let mut index = 0;
+    for node in nodes {
+        let mut headings = vec![];
+        collect_headings(wikitext, std::slice::from_ref(node), &mut headings);
+        if !matches!(node.value, WikitextSimplifiedNode::Heading { .. }) {
+            match open.last_mut() {
+                Some(section) => section.children.push(node.clone()),
+                None => lead.children.push(node.clone()),
+            }
+        }
+
+        for heading in headings {
+            let WikitextSimplifiedNode::Heading { level, children } = &heading.value else {
+                continue;
+            };
+            if index == 0 {
+                lead.span.end = heading.span.start;
+            }
+            while open.last().is_some_and(|section| section.level >= *level) {
+                close_section(&mut open, &mut sections, heading.span.start);
+            }
+            index += 1;
+            open.push(Section {
+                index,
+                level: *level,
+                heading: Some(children.clone()),
+                children: vec![],
+                subsections: vec![],
+                span: Span {
+                    start: heading.span.start,
+                    end,
+                },
+            });
+        }
+    }
+    while !open.is_empty() {
+        close_section(&mut open, &mut sections, end);
+    }
+
+    std::iter::once(lead).chain(sections).collect()
+}
+
+/// Ends the innermost open section at the given position, adding it to its parent or to
+/// the top-level sections.
+fn close_section(open: &mut Vec<Section>, sections: &mut Vec<Section>, end: usize) {
+    let Some(mut section) = open.pop() else {
+        return;
+    };
+    section.span.end = end;
+    match open.last_mut() {
+        Some(parent) => parent.subsections.push(section),
+        None => sections.push(section),
+    }
+}
+
+/// Returns the section with the given index from sections returned by
+/// [`collect_sections`], if it exists.
+pub fn find_section(sections: &[Section], index: usize) -> Option<&Section> {
+    sections.iter().find_map(|section| section.find(index))
+}
+
+/// Returns the source of the section with the given index, as MediaWiki's `section=N`
+/// parameter does, or `None` if there is no such section.
+///
+/// Section 0 is the lead, before the first heading, and every other section includes its
+/// subsections. Sections are numbered as with [`collect_sections`], and as with MediaWiki,
+/// trailing whitespace is removed.
+///
+/// # Errors
+///
+/// This function will return an error if the wikitext cannot be parsed.
+pub fn get_section<'a>(
+    wikitext: &'a str,
+    pwt_configuration: &pwt::Configuration,
+    index: usize,
+) -> Result<Option<&'a str>, pwt::ParseError<'a>> {
+    let span = section_span(wikitext, pwt_configuration, index)?;
+    Ok(span.map(|span| wikitext[span.start..span.end].trim_end()))
+}
+
+/// Replaces the section with the given index, as defined by [`get_section`], with the given
+/// text, returning the new wikitext, or `None` if there is no such section.
+///
+/// As with MediaWiki, the new text is separated from any following section by a blank line,
+/// and trailing whitespace is removed from the result.
+///
+/// # Errors
+///
+/// This function will return an error if the wikitext cannot be parsed.
+pub fn replace_section<'a>(
+    wikitext: &'a str,
+    pwt_configuration: &pwt::Configuration,
+    index: usize,
+    new_text: &str,
+) -> Result<Option<String>, pwt::ParseError<'a>> {
+    let Some(span) = section_span(wikitext, pwt_configuration, index)? else {
+        return Ok(None);
+    };
+    let output = format!(
+        "{}{new_text}\n\n{}",
+        &wikitext[..span.start],
+        &wikitext[span.end..]
+    );
+    Ok(Some(output.trim_end().to_string()))
+}
+
+/// Finds the span of the section with the given index in the wikitext.
+fn section_span<'a>(
+    wikitext: &'a str,
+    pwt_configuration: &pwt::Configuration,
+    index: usize,
+) -> Result<Option<Span>, pwt::ParseError<'a>> {
+    let output = pwt_configuration.parse(wikitext)?;
+    let (nodes, _) = simplify_wikitext_nodes_recovering(
+        wikitext,
+        &output.nodes,
+        &SimplificationConfig::default(),
+    );
+    let mut headings = vec![];
+    collect_headings(wikitext, &nodes, &mut headings);
+    let headings: Vec<(usize, u8)> = headings
+        .into_iter()
+        .filter_map(|heading| match heading.value {
+            WikitextSimplifiedNode::Heading { level, .. } => Some((heading.span.start, level)),
+            _ => None,
+        })
+        .collect();
+
+    let end = wikitext.len();
+    if index == 0 {
+        let lead_end = headings.first().map_or(end, |(start, _)| *start);
+        return Ok(Some(Span {
+            start: 0,
+            end: lead_end,
+        }));
+    }
+    let Some(&(start, level)) = headings.get(index - 1) else {
+        return Ok(None);
+    };
+    let end = headings[index..]
+        .iter()
+        .find(|(_, next_level)| *next_level <= level)
+        .map_or(end, |(next_start, _)| *next_start);
+    Ok(Some(Span { start, end }))
+}
+
+/// Extension tags used by Wikipedia, within which headings do not start sections
+const EXTENSION_TAGS: [&str; 26] = [
+    "categorytree",
+    "ce",
+    "charinsert",
+    "chem",
+    "gallery",
+    "graph",
+    "hiero",
+    "imagemap",
+    "indicator",
+    "inputbox",
+    "langconvert",
+    "mapframe",
+    "maplink",
+    "math",
+    "nowiki",
+    "poem",
+    "pre",
+    "ref",
+    "references",
+    "score",
+    "section",
+    "source",
+    "syntaxhighlight",
+    "templatedata",
+    "templatestyles",
+    "timeline",
+];
+
+/// Collects the headings that MediaWiki would treat as starting a section, in the order
+/// they appear, i.e. those that are not within templates or extension tags.
+///
+/// The wikitext is used to tell `<pre>` tags apart from lines starting with a space, and
+/// should be the wikitext the nodes were parsed from.
+fn collect_headings<'a>(
+    wikitext: &str,
+    nodes: &'a [Spanned<WikitextSimplifiedNode>],
+    headings: &mut Vec<&'a Spanned<WikitextSimplifiedNode>>,
+) {
+    use WikitextSimplifiedNode as WSN;
+    for node in nodes {
+        // `<pre>` tags are simplified into preformatted nodes, but are extension tags
+        let is_pre_tag = wikitext
+            .get(node.span.start..node.span.start + 4)
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("<pre"));
+        match &node.value {
+            WSN::Heading { .. } => headings.push(node),
+            WSN::Reference { .. } | WSN::ReferenceList { .. } => {}
+            WSN::Preformatted { .. } if is_pre_tag => {}
+            WSN::Tag { name, .. }
+                if EXTENSION_TAGS.contains(&name.to_ascii_lowercase().as_str()) => {}
+            WSN::Table { captions, rows, .. } => {
+                for caption in captions {
+                    collect_headings(wikitext, &caption.content, headings);
+                }
+                for cell in rows.iter().flat_map(|row| &row.cells) {
+                    collect_headings(wikitext, &cell.content, headings);
+                }
+            }
+            value => {
+                if let Some(children) = value.children() {
+                    collect_headings(wikitext, children, headings);
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,19 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
 
 use parse_wiki_text_2 as pwt;
-use wikitext_util::{nodes_inner_text, nodes_wikitext, NodeMetadata, NodeMetadataType};
+use wikitext_util::{
+    nodes_inner_text, nodes_wikitext, wikipedia_link_prefixes, LinkPrefixes, NodeMetadata,
+    NodeMetadataType,
+};
+
+use crate::attributes::{find_tag_attribute, parse_tag_attributes};
+use crate::diagnostics::DiagnosticCollector;
+use crate::quotes::{Quote, QuoteAction, QuoteResolution};
+use crate::{
+    Diagnostic, DiagnosticKind, DiagnosticSeverity, LinkTarget, TagAttribute, TemplateName,
+};
 
 #[cfg(feature = "wasm")]
 use tsify_next::Tsify;
@@ -102,17 +114,8 @@ pub enum NodeStructureError {
         /// The type of node that has no children
         parent_node_type: &'static str,
     },
-    /// Found a bold-italic node without a corresponding bold node
-    MissingBoldLayer,
     /// Found an unclosed formatting node
     UnclosedFormatting,
-    /// Found a tag closure mismatch, where the closing tag does not match the opening tag
-    TagClosureMismatch {
-        /// The expected tag name
-        expected: String,
-        /// The actual tag name
-        actual: String,
-    },
 }
 impl std::fmt::Display for NodeStructureError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -122,13 +125,7 @@ impl std::fmt::Display for NodeStructureError {
             NodeStructureError::NoChildren { parent_node_type } => {
                 write!(f, "Node of type '{parent_node_type}' has no children")
             }
-            NodeStructureError::MissingBoldLayer => {
-                write!(f, "Bold-italic found without a bold layer")
-            }
             NodeStructureError::UnclosedFormatting => write!(f, "Unclosed formatting node"),
-            NodeStructureError::TagClosureMismatch { expected, actual } => {
-                write!(f, "Tag closure mismatch: {actual} (expected {expected})")
-            }
         }
     }
 }
@@ -149,8 +146,12 @@ pub enum WikitextSimplifiedNode {
     },
     /// A template node, representing a wikitext template
     Template {
-        /// The name of the template
+        /// The name of the template, as written
         name: String,
+        /// The page transcluded by the template, resolved from its name
+        normalized_name: TemplateName,
+        /// The span of the name of the template in the source
+        name_span: Span,
         /// The parameters passed to the template
         parameters: Vec<TemplateParameter>,
     },
@@ -170,17 +171,57 @@ pub enum WikitextSimplifiedNode {
     },
     /// An internal wiki link
     Link {
-        /// The display text of the link
-        text: String,
-        /// The target page of the link
+        /// The display text of the link, including any link trail (e.g. the `s` in `[[cat]]s`)
+        text: Vec<Spanned<WikitextSimplifiedNode>>,
+        /// The target page of the link, as written
         title: String,
+        /// The target of the link, resolved from its title
+        target: LinkTarget,
     },
     /// An external link
     ExtLink {
-        /// The URL of the external link
+        /// The URL of the external link, as written.
+        ///
+        /// This may include templates (e.g. `https://example.com/{{PAGENAME}}`).
         link: String,
-        /// Optional display text for the link
-        text: Option<String>,
+        /// The protocol of the URL, as written (e.g. `https://`, or `//` for protocol-relative URLs)
+        protocol: String,
+        /// The display text of the link, if any
+        text: Option<Vec<Spanned<WikitextSimplifiedNode>>>,
+        /// Whether the link was written in square brackets, rather than being a bare URL in
+        /// text that was linked automatically
+        bracketed: bool,
+    },
+    /// An embedded image or other media file
+    Image {
+        /// The file being embedded, as written (e.g. `File:Example.jpg`)
+        file: String,
+        /// The display options of the image
+        options: ImageOptions,
+        /// The caption of the image
+        caption: Vec<Spanned<WikitextSimplifiedNode>>,
+    },
+    /// A gallery of images, i.e. `<gallery>`
+    Gallery {
+        /// The HTML attributes of the gallery
+        attributes: Vec<TagAttribute>,
+        /// The HTML attributes of the gallery as written, used when converting back to
+        /// wikitext
+        raw_attributes: Option<String>,
+        /// The images in the gallery, one per line
+        entries: Vec<WikitextSimplifiedGalleryEntry>,
+    },
+    /// A behavior switch (e.g. `__NOTOC__`)
+    BehaviorSwitch {
+        /// The name of the switch in uppercase, without the surrounding underscores (e.g. `NOTOC`)
+        name: String,
+    },
+    /// A category assignment (e.g. `[[Category:Foo|Sort key]]`)
+    Category {
+        /// The name of the category, without the namespace prefix
+        name: String,
+        /// The sort key, if specified
+        sort_key: Option<String>,
     },
     /// Bold text formatting
     Bold {
@@ -222,10 +263,41 @@ pub enum WikitextSimplifiedNode {
         /// The name of the tag
         name: String,
         /// The HTML attributes of the tag
-        attributes: Option<String>,
+        attributes: Vec<TagAttribute>,
+        /// The HTML attributes of the tag as written, used when converting back to wikitext
+        raw_attributes: Option<String>,
         /// The content within the tag
         children: Vec<Spanned<WikitextSimplifiedNode>>,
     },
+    /// A footnote reference, i.e. `<ref>`.
+    ///
+    /// A reference with a name and no children is a reuse of a named reference defined
+    /// elsewhere; see [`crate::collect_footnotes`] for resolving these.
+    Reference {
+        /// The name of the reference, if specified
+        name: Option<String>,
+        /// The group of the reference, if specified
+        group: Option<String>,
+        /// The content of the reference
+        children: Vec<Spanned<WikitextSimplifiedNode>>,
+    },
+    /// A list of footnotes, i.e. `<references>`.
+    ReferenceList {
+        /// The group of footnotes to list, if specified
+        group: Option<String>,
+        /// Any list-defined references within the list
+        children: Vec<Spanned<WikitextSimplifiedNode>>,
+    },
+    /// Literal text from a `<nowiki>` tag, which is not parsed as wikitext.
+    Nowiki {
+        /// The raw text within the tag
+        text: String,
+    },
+    /// An HTML comment, only produced if [`SimplificationConfig::preserve_comments`] is set
+    Comment {
+        /// The text within the comment, excluding the `<!--` and `-->` markers
+        text: String,
+    },
     /// Plain text content
     Text {
         /// The text content
@@ -299,14 +371,39 @@ pub struct WikitextSimplifiedTableCell {
     /// The content of the cell
     pub content: Vec<Spanned<WikitextSimplifiedNode>>,
 }
+/// An image in a gallery
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct WikitextSimplifiedGalleryEntry {
+    /// The file being displayed, with its namespace canonicalized, or with a `File:` prefix
+    /// added if it was written without one
+    pub file: String,
+    /// The display options of the image
+    pub options: ImageOptions,
+    /// The caption of the image
+    pub caption: Vec<Spanned<WikitextSimplifiedNode>>,
+}
 /// A list item
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "wasm", derive(Tsify))]
 #[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct WikitextSimplifiedListItem {
-    /// The content of the list item
+    /// The full sequence of list markers at the start of the item's line, e.g. `*#` for an
+    /// ordered list item nested within an unordered list.
+    ///
+    /// This may be empty for items that were not parsed from wikitext, in which case the
+    /// marker is derived from the nesting of the lists when converting back to wikitext.
+    pub marker: String,
+    /// The content of the list item, including any lists nested within it
     pub content: Vec<Spanned<WikitextSimplifiedNode>>,
 }
+impl WikitextSimplifiedListItem {
+    /// Returns the nesting depth of the item, starting from 1 for top-level items.
+    pub fn depth(&self) -> usize {
+        self.marker.chars().count()
+    }
+}
 /// A list item in a definition list
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "wasm", derive(Tsify))]
@@ -314,9 +411,24 @@ pub struct WikitextSimplifiedListItem {
 pub struct WikitextSimplifiedDefinitionListItem {
     /// The type of list item
     pub type_: DefinitionListItemType,
-    /// The content of the list item
+    /// The full sequence of list markers at the start of the item's line, e.g. `*:` for
+    /// details nested within an unordered list.
+    ///
+    /// For details that follow a term on the same line (e.g. `;term:details`), this is the
+    /// marker of the term with its last `;` replaced by `:`.
+    ///
+    /// This may be empty for items that were not parsed from wikitext, in which case the
+    /// marker is derived from the nesting of the lists when converting back to wikitext.
+    pub marker: String,
+    /// The content of the list item, including any lists nested within it
     pub content: Vec<Spanned<WikitextSimplifiedNode>>,
 }
+impl WikitextSimplifiedDefinitionListItem {
+    /// Returns the nesting depth of the item, starting from 1 for top-level items.
+    pub fn depth(&self) -> usize {
+        self.marker.chars().count()
+    }
+}
 /// The type of a definition list item
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "wasm", derive(Tsify))]
@@ -335,6 +447,221 @@ impl std::fmt::Display for DefinitionListItemType {
         }
     }
 }
+/// The display options of an image
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ImageOptions {
+    /// The display format (`thumb`, `frame`, `frameless`)
+    pub format: Option<ImageFormat>,
+    /// Whether the image has a border (`border`)
+    pub border: bool,
+    /// The requested size (e.g. `200px`, `x100px`, `200x100px`)
+    pub size: Option<ImageSize>,
+    /// The horizontal alignment (`left`, `right`, `center`, `none`)
+    pub alignment: Option<ImageAlignment>,
+    /// The vertical alignment (`baseline`, `middle`, `top`, etc)
+    pub vertical_alignment: Option<ImageVerticalAlignment>,
+    /// The `upright` scaling factor as written; empty if `upright` was given without a value
+    pub upright: Option<String>,
+    /// The alternative text (`alt=`)
+    pub alt: Option<String>,
+    /// The link target (`link=`); empty if the image should not link anywhere
+    pub link: Option<String>,
+}
+/// The display format of an image
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ImageFormat {
+    /// A thumbnail with a caption (`thumb`, `thumbnail`)
+    Thumbnail,
+    /// A framed image at its original size (`frame`, `framed`, `enframed`)
+    Frame,
+    /// A thumbnail-sized image without a frame (`frameless`)
+    Frameless,
+}
+/// The requested size of an image, in pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ImageSize {
+    /// The maximum width, if specified
+    pub width: Option<u32>,
+    /// The maximum height, if specified
+    pub height: Option<u32>,
+}
+/// The horizontal alignment of an image
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ImageAlignment {
+    /// `left`
+    Left,
+    /// `right`
+    Right,
+    /// `center` or `centre`
+    Center,
+    /// `none`
+    None,
+}
+/// The vertical alignment of an inline image
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ImageVerticalAlignment {
+    /// `baseline`
+    Baseline,
+    /// `sub`
+    Sub,
+    /// `super`
+    Super,
+    /// `top`
+    Top,
+    /// `text-top`
+    TextTop,
+    /// `middle`
+    Middle,
+    /// `bottom`
+    Bottom,
+    /// `text-bottom`
+    TextBottom,
+}
+impl ImageOptions {
+    /// Applies a single `|`-separated image option to these options.
+    ///
+    /// Returns `false` if the option was not recognised, in which case MediaWiki
+    /// treats it as a caption.
+    pub fn apply(&mut self, option: &str) -> bool {
+        let option = option.trim();
+        if let Some((key, value)) = option.split_once('=') {
+            let value = value.trim().to_string();
+            match key.trim() {
+                "alt" => self.alt = Some(value),
+                "link" => self.link = Some(value),
+                "upright" => self.upright = Some(value),
+                _ => return false,
+            }
+            return true;
+        }
+
+        match option {
+            "thumb" | "thumbnail" => self.format = Some(ImageFormat::Thumbnail),
+            "frame" | "framed" | "enframed" => self.format = Some(ImageFormat::Frame),
+            "frameless" => self.format = Some(ImageFormat::Frameless),
+            "border" => self.border = true,
+            "upright" => self.upright = Some(String::new()),
+            "left" => self.alignment = Some(ImageAlignment::Left),
+            "right" => self.alignment = Some(ImageAlignment::Right),
+            "center" | "centre" => self.alignment = Some(ImageAlignment::Center),
+            "none" => self.alignment = Some(ImageAlignment::None),
+            "baseline" => self.vertical_alignment = Some(ImageVerticalAlignment::Baseline),
+            "sub" => self.vertical_alignment = Some(ImageVerticalAlignment::Sub),
+            "super" => self.vertical_alignment = Some(ImageVerticalAlignment::Super),
+            "top" => self.vertical_alignment = Some(ImageVerticalAlignment::Top),
+            "text-top" => self.vertical_alignment = Some(ImageVerticalAlignment::TextTop),
+            "middle" => self.vertical_alignment = Some(ImageVerticalAlignment::Middle),
+            "bottom" => self.vertical_alignment = Some(ImageVerticalAlignment::Bottom),
+            "text-bottom" => self.vertical_alignment = Some(ImageVerticalAlignment::TextBottom),
+            _ => match ImageSize::parse(option) {
+                Some(size) => self.size = Some(size),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    /// Returns the options in their wikitext form, in a canonical order.
+    pub fn to_wikitext_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if let Some(format) = self.format {
+            options.push(
+                match format {
+                    ImageFormat::Thumbnail => "thumb",
+                    ImageFormat::Frame => "frame",
+                    ImageFormat::Frameless => "frameless",
+                }
+                .to_string(),
+            );
+        }
+        if self.border {
+            options.push("border".to_string());
+        }
+        if let Some(alignment) = self.alignment {
+            options.push(
+                match alignment {
+                    ImageAlignment::Left => "left",
+                    ImageAlignment::Right => "right",
+                    ImageAlignment::Center => "center",
+                    ImageAlignment::None => "none",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(vertical_alignment) = self.vertical_alignment {
+            options.push(
+                match vertical_alignment {
+                    ImageVerticalAlignment::Baseline => "baseline",
+                    ImageVerticalAlignment::Sub => "sub",
+                    ImageVerticalAlignment::Super => "super",
+                    ImageVerticalAlignment::Top => "top",
+                    ImageVerticalAlignment::TextTop => "text-top",
+                    ImageVerticalAlignment::Middle => "middle",
+                    ImageVerticalAlignment::Bottom => "bottom",
+                    ImageVerticalAlignment::TextBottom => "text-bottom",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(size) = self.size {
+            options.push(size.to_string());
+        }
+        match self.upright.as_deref() {
+            Some("") => options.push("upright".to_string()),
+            Some(factor) => options.push(format!("upright={factor}")),
+            None => {}
+        }
+        if let Some(link) = &self.link {
+            options.push(format!("link={link}"));
+        }
+        if let Some(alt) = &self.alt {
+            options.push(format!("alt={alt}"));
+        }
+        options
+    }
+}
+impl ImageSize {
+    /// Parses a size option of the form `200px`, `x100px` or `200x100px`.
+    pub fn parse(option: &str) -> Option<Self> {
+        let dimensions = option.trim().strip_suffix("px")?.trim_end();
+        let parse_dimension = |s: &str| -> Option<Option<u32>> {
+            if s.is_empty() {
+                Some(None)
+            } else {
+                s.parse().ok().map(Some)
+            }
+        };
+        let (width, height) = match dimensions.split_once('x') {
+            Some((width, height)) => (parse_dimension(width)?, parse_dimension(height)?),
+            None => (parse_dimension(dimensions)?, None),
+        };
+        if width.is_none() && height.is_none() {
+            return None;
+        }
+        Some(Self { width, height })
+    }
+}
+impl std::fmt::Display for ImageSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(width) = self.width {
+            write!(f, "{width}")?;
+        }
+        if let Some(height) = self.height {
+            write!(f, "x{height}")?;
+        }
+        write!(f, "px")
+    }
+}
 impl WikitextSimplifiedNode {
     /// Returns the type of this node.
     pub fn node_type(&self) -> &'static str {
@@ -345,6 +672,10 @@ impl WikitextSimplifiedNode {
             Self::Heading { .. } => "heading",
             Self::Link { .. } => "link",
             Self::ExtLink { .. } => "ext-link",
+            Self::Image { .. } => "image",
+            Self::Gallery { .. } => "gallery",
+            Self::BehaviorSwitch { .. } => "behavior-switch",
+            Self::Category { .. } => "category",
             Self::Bold { .. } => "bold",
             Self::Italic { .. } => "italic",
             Self::Blockquote { .. } => "blockquote",
@@ -353,6 +684,10 @@ impl WikitextSimplifiedNode {
             Self::Small { .. } => "small",
             Self::Preformatted { .. } => "preformatted",
             Self::Tag { .. } => "tag",
+            Self::Reference { .. } => "reference",
+            Self::ReferenceList { .. } => "reference-list",
+            Self::Nowiki { .. } => "nowiki",
+            Self::Comment { .. } => "comment",
             Self::Text { .. } => "text",
             Self::Table { .. } => "table",
             Self::OrderedList { .. } => "ordered-list",
@@ -382,11 +717,19 @@ impl WikitextSimplifiedNode {
             Self::Small { children } => Some(children),
             Self::Preformatted { children } => Some(children),
             Self::Tag { children, .. } => Some(children),
+            Self::Reference { children, .. } => Some(children),
+            Self::ReferenceList { children, .. } => Some(children),
 
             Self::Template { .. }
             | Self::TemplateParameterUse { .. }
             | Self::Link { .. }
             | Self::ExtLink { .. }
+            | Self::Image { .. }
+            | Self::Gallery { .. }
+            | Self::BehaviorSwitch { .. }
+            | Self::Category { .. }
+            | Self::Nowiki { .. }
+            | Self::Comment { .. }
             | Self::Text { .. }
             | Self::Table { .. }
             | Self::OrderedList { .. }
@@ -416,11 +759,19 @@ impl WikitextSimplifiedNode {
             Self::Small { children } => Some(children),
             Self::Preformatted { children } => Some(children),
             Self::Tag { children, .. } => Some(children),
+            Self::Reference { children, .. } => Some(children),
+            Self::ReferenceList { children, .. } => Some(children),
 
             Self::Template { .. }
             | Self::TemplateParameterUse { .. }
             | Self::Link { .. }
             | Self::ExtLink { .. }
+            | Self::Image { .. }
+            | Self::Gallery { .. }
+            | Self::BehaviorSwitch { .. }
+            | Self::Category { .. }
+            | Self::Nowiki { .. }
+            | Self::Comment { .. }
             | Self::Text { .. }
             | Self::Table { .. }
             | Self::OrderedList { .. }
@@ -462,17 +813,21 @@ impl WikitextSimplifiedNode {
 
         match self {
             Self::Fragment { children } => nodes_to_wikitext(children),
-            Self::Template { name, parameters } => {
+            Self::Template {
+                name, parameters, ..
+            } => {
+                let mut next_positional_index = 1;
                 let params = parameters
                     .iter()
                     .map(|param| {
-                        if param.name == "1" {
-                            param.value.clone()
-                        } else if param.name.parse::<usize>().is_ok() {
-                            // For numeric parameters, just use the value
-                            param.value.clone()
+                        let value = nodes_to_wikitext(&param.value);
+                        // Numeric parameters can be written positionally if they are next in
+                        // sequence, and their value would not be mistaken for a name
+                        if param.name == next_positional_index.to_string() && !value.contains('=') {
+                            next_positional_index += 1;
+                            value
                         } else {
-                            format!("{}={}", param.name, param.value)
+                            format!("{}={}", param.name, value)
                         }
                     })
                     .collect::<Vec<_>>()
@@ -498,18 +853,70 @@ impl WikitextSimplifiedNode {
                 let equals = "=".repeat(*level as usize);
                 format!("{} {} {}", equals, nodes_to_wikitext(children), equals)
             }
-            Self::Link { text, title } => {
-                if text == title {
+            Self::Link { text, title, .. } => {
+                let text = nodes_to_wikitext(text);
+                if text == *title {
                     format!("[[{title}]]")
                 } else {
                     format!("[[{title}|{text}]]")
                 }
             }
-            Self::ExtLink { link, text } => {
-                if let Some(text) = text {
-                    format!("[{link} {text}]")
+            Self::ExtLink {
+                link,
+                text,
+                bracketed,
+                ..
+            } => match text {
+                Some(text) => format!("[{link} {}]", nodes_to_wikitext(text)),
+                None if *bracketed => format!("[{link}]"),
+                None => link.clone(),
+            },
+            Self::Image {
+                file,
+                options,
+                caption,
+            } => {
+                let mut result = format!("[[{file}");
+                for option in options.to_wikitext_options() {
+                    result.push('|');
+                    result.push_str(&option);
+                }
+                if !caption.is_empty() {
+                    result.push('|');
+                    result.push_str(&nodes_to_wikitext(caption));
+                }
+                result.push_str("]]");
+                result
+            }
+            Self::Gallery {
+                raw_attributes,
+                entries,
+                ..
+            } => {
+                let attrs = raw_attributes.as_deref().unwrap_or("");
+                let space = if attrs.is_empty() { "" } else { " " };
+                let mut result = format!("<gallery{space}{attrs}>\n");
+                for entry in entries {
+                    result.push_str(&entry.file);
+                    for option in entry.options.to_wikitext_options() {
+                        result.push('|');
+                        result.push_str(&option);
+                    }
+                    if !entry.caption.is_empty() {
+                        result.push('|');
+                        result.push_str(&nodes_to_wikitext(&entry.caption));
+                    }
+                    result.push('\n');
+                }
+                result.push_str("</gallery>");
+                result
+            }
+            Self::BehaviorSwitch { name } => format!("__{name}__"),
+            Self::Category { name, sort_key } => {
+                if let Some(sort_key) = sort_key {
+                    format!("[[Category:{name}|{sort_key}]]")
                 } else {
-                    format!("[{link}]")
+                    format!("[[Category:{name}]]")
                 }
             }
             Self::Bold { children } => {
@@ -535,10 +942,11 @@ impl WikitextSimplifiedNode {
             }
             Self::Tag {
                 name,
-                attributes,
+                raw_attributes,
                 children,
+                ..
             } => {
-                let attrs = attributes.as_deref().unwrap_or("");
+                let attrs = raw_attributes.as_deref().unwrap_or("");
                 let space = if attrs.is_empty() { "" } else { " " };
                 format!(
                     "<{}{}{}>{}</{}>",
@@ -549,6 +957,39 @@ impl WikitextSimplifiedNode {
                     name
                 )
             }
+            Self::Reference {
+                name,
+                group,
+                children,
+            } => {
+                let attrs = reference_attributes_to_wikitext(name.as_deref(), group.as_deref());
+                if children.is_empty() {
+                    format!("<ref{attrs} />")
+                } else {
+                    format!("<ref{attrs}>{}</ref>", nodes_to_wikitext(children))
+                }
+            }
+            Self::ReferenceList { group, children } => {
+                let attrs = reference_attributes_to_wikitext(None, group.as_deref());
+                if children.is_empty() {
+                    format!("<references{attrs} />")
+                } else {
+                    format!(
+                        "<references{attrs}>{}</references>",
+                        nodes_to_wikitext(children)
+                    )
+                }
+            }
+            // The tag is always kept, as even plain text can become markup when joined with
+            // the text around it (e.g. a link trail after `[[X]]`, or `''` between apostrophes)
+            Self::Nowiki { text } => {
+                if text.is_empty() {
+                    "<nowiki/>".to_string()
+                } else {
+                    format!("<nowiki>{text}</nowiki>")
+                }
+            }
+            Self::Comment { text } => format!("<!--{text}-->"),
             Self::Text { text } => text.replace('\u{a0}', "&nbsp;"),
             Self::Table {
                 attributes,
@@ -600,32 +1041,8 @@ impl WikitextSimplifiedNode {
                 result.push_str("|}\n");
                 result
             }
-            Self::OrderedList { items } => {
-                let mut result = String::new();
-                for item in items {
-                    result.push('#');
-                    result.push_str(&nodes_to_wikitext(&item.content));
-                    result.push('\n');
-                }
-                result
-            }
-            Self::UnorderedList { items } => {
-                let mut result = String::new();
-                for item in items {
-                    result.push('*');
-                    result.push_str(&nodes_to_wikitext(&item.content));
-                    result.push('\n');
-                }
-                result
-            }
-            Self::DefinitionList { items } => {
-                let mut result = String::new();
-                for item in items {
-                    result.push_str(&item.type_.to_string());
-                    result.push_str(&nodes_to_wikitext(&item.content));
-                    result.push('\n');
-                }
-                result
+            Self::OrderedList { .. } | Self::UnorderedList { .. } | Self::DefinitionList { .. } => {
+                self.list_to_wikitext("")
             }
             Self::Redirect { target } => {
                 format!("#REDIRECT [[{target}]]")
@@ -635,96 +1052,289 @@ impl WikitextSimplifiedNode {
             Self::Newline => "\n".to_string(),
         }
     }
+
+    /// Converts a list to wikitext, writing each item with its marker, or with the
+    /// marker of its parent item followed by its own if it has none.
+    fn list_to_wikitext(&self, parent_marker: &str) -> String {
+        let items: Vec<(String, &[Spanned<WikitextSimplifiedNode>])> = match self {
+            Self::OrderedList { items } | Self::UnorderedList { items } => {
+                let own_marker = if matches!(self, Self::OrderedList { .. }) {
+                    "#"
+                } else {
+                    "*"
+                };
+                items
+                    .iter()
+                    .map(|item| {
+                        let marker = if item.marker.is_empty() {
+                            format!("{parent_marker}{own_marker}")
+                        } else {
+                            item.marker.clone()
+                        };
+                        (marker, item.content.as_slice())
+                    })
+                    .collect()
+            }
+            Self::DefinitionList { items } => items
+                .iter()
+                .map(|item| {
+                    let marker = if item.marker.is_empty() {
+                        format!("{parent_marker}{}", item.type_)
+                    } else {
+                        item.marker.clone()
+                    };
+                    (marker, item.content.as_slice())
+                })
+                .collect(),
+            _ => return self.to_wikitext(),
+        };
+
+        let mut result = String::new();
+        for (marker, content) in items {
+            // An item that only contains a nested list is written as just that list
+            let starts_with_list = content.first().is_some_and(|node| node.value.is_list());
+            if !starts_with_list {
+                result.push_str(&marker);
+            }
+            for node in content {
+                if node.value.is_list() {
+                    if !result.is_empty() && !result.ends_with('\n') {
+                        result.push('\n');
+                    }
+                    result.push_str(&node.value.list_to_wikitext(&marker));
+                } else {
+                    result.push_str(&node.value.to_wikitext());
+                }
+            }
+            if !result.ends_with('\n') {
+                result.push('\n');
+            }
+        }
+        result
+    }
+
+    /// Returns whether this is an ordered, unordered or definition list.
+    fn is_list(&self) -> bool {
+        matches!(
+            self,
+            Self::OrderedList { .. } | Self::UnorderedList { .. } | Self::DefinitionList { .. }
+        )
+    }
+
+    /// Returns the marker of the first item of this node if it is a list.
+    fn first_list_item_marker(&self) -> Option<&str> {
+        match self {
+            Self::OrderedList { items } | Self::UnorderedList { items } => {
+                items.first().map(|item| item.marker.as_str())
+            }
+            Self::DefinitionList { items } => items.first().map(|item| item.marker.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Converts this node and its children into plain text, as it would be displayed without
+    /// any formatting (e.g. the display text of a [`WikitextSimplifiedNode::Link`]).
+    ///
+    /// Nodes that do not display text inline, such as templates, images, categories and
+    /// references, are omitted.
+    pub fn to_plain_text(&self) -> String {
+        fn nodes_to_plain_text(nodes: &[Spanned<WikitextSimplifiedNode>]) -> String {
+            nodes
+                .iter()
+                .map(|node| node.value.to_plain_text())
+                .collect()
+        }
+
+        match self {
+            Self::Text { text } | Self::Nowiki { text } => text.clone(),
+            Self::Link { text, .. } => nodes_to_plain_text(text),
+            Self::ExtLink { link, text, .. } => match text {
+                Some(text) => nodes_to_plain_text(text),
+                None => link.clone(),
+            },
+            Self::OrderedList { items } | Self::UnorderedList { items } => items
+                .iter()
+                .map(|item| nodes_to_plain_text(&item.content))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::DefinitionList { items } => items
+                .iter()
+                .map(|item| nodes_to_plain_text(&item.content))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Table { rows, .. } => rows
+                .iter()
+                .map(|row| {
+                    row.cells
+                        .iter()
+                        .map(|cell| nodes_to_plain_text(&cell.content))
+                        .collect::<Vec<_>>()
+                        .join("\t")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Reference { .. } | Self::ReferenceList { .. } => String::new(),
+            Self::ParagraphBreak | Self::Newline => "\n".to_string(),
+            _ => self.children().map(nodes_to_plain_text).unwrap_or_default(),
+        }
+    }
 }
 // Visitors
 macro_rules! visit_children_impl {
-    ($self:expr, $visitor:expr, $visit_method:ident, $iter_method:ident) => {
-        match $self {
-            Self::Fragment { children }
-            | Self::Heading { children, .. }
-            | Self::Bold { children }
-            | Self::Italic { children }
-            | Self::Blockquote { children }
-            | Self::Superscript { children }
-            | Self::Subscript { children }
-            | Self::Small { children }
-            | Self::Preformatted { children }
-            | Self::Tag { children, .. } => {
-                for child in children {
-                    child.value.$visit_method($visitor);
+    ($node:expr, $iter_method:ident, |$child:ident| $visit:expr) => {
+        match $node {
+            WikitextSimplifiedNode::Fragment { children }
+            | WikitextSimplifiedNode::Heading { children, .. }
+            | WikitextSimplifiedNode::Bold { children }
+            | WikitextSimplifiedNode::Italic { children }
+            | WikitextSimplifiedNode::Blockquote { children }
+            | WikitextSimplifiedNode::Superscript { children }
+            | WikitextSimplifiedNode::Subscript { children }
+            | WikitextSimplifiedNode::Small { children }
+            | WikitextSimplifiedNode::Preformatted { children }
+            | WikitextSimplifiedNode::Tag { children, .. }
+            | WikitextSimplifiedNode::Reference { children, .. }
+            | WikitextSimplifiedNode::ReferenceList { children, .. } => {
+                for $child in children {
+                    $visit;
                 }
             }
 
-            Self::TemplateParameterUse { default, .. } => {
+            WikitextSimplifiedNode::Template { parameters, .. } => {
+                for $child in parameters
+                    .$iter_method()
+                    .flat_map(|p| p.value.$iter_method())
+                {
+                    $visit;
+                }
+            }
+            WikitextSimplifiedNode::TemplateParameterUse { default, .. } => {
                 if let Some(default) = default {
-                    for child in default {
-                        child.value.$visit_method($visitor);
+                    for $child in default {
+                        $visit;
                     }
                 }
             }
-            Self::Table {
+            WikitextSimplifiedNode::Link { text, .. } => {
+                for $child in text {
+                    $visit;
+                }
+            }
+            WikitextSimplifiedNode::ExtLink { text, .. } => {
+                if let Some(text) = text {
+                    for $child in text {
+                        $visit;
+                    }
+                }
+            }
+            WikitextSimplifiedNode::Image { caption, .. } => {
+                for $child in caption {
+                    $visit;
+                }
+            }
+            WikitextSimplifiedNode::Gallery { entries, .. } => {
+                for $child in entries
+                    .$iter_method()
+                    .flat_map(|e| e.caption.$iter_method())
+                {
+                    $visit;
+                }
+            }
+            WikitextSimplifiedNode::Table {
                 attributes,
                 captions,
                 rows,
                 ..
             } => {
-                for attr in attributes.$iter_method() {
-                    attr.value.$visit_method($visitor);
+                for $child in attributes.$iter_method() {
+                    $visit;
                 }
-                for caption in captions.$iter_method().flat_map(|c| {
+                for $child in captions.$iter_method().flat_map(|c| {
                     c.content
                         .$iter_method()
                         .chain(c.attributes.$iter_method().flat_map(|a| a.$iter_method()))
                 }) {
-                    caption.value.$visit_method($visitor);
+                    $visit;
                 }
                 for row in rows.$iter_method() {
-                    for cell in row.cells.$iter_method().flat_map(|c| {
+                    for $child in row.cells.$iter_method().flat_map(|c| {
                         c.content
                             .$iter_method()
                             .chain(c.attributes.$iter_method().flat_map(|a| a.$iter_method()))
                     }) {
-                        cell.value.$visit_method($visitor);
+                        $visit;
                     }
                 }
             }
-            Self::OrderedList { items } => {
-                for item in items.$iter_method().flat_map(|i| i.content.$iter_method()) {
-                    item.value.$visit_method($visitor);
+            WikitextSimplifiedNode::OrderedList { items } => {
+                for $child in items.$iter_method().flat_map(|i| i.content.$iter_method()) {
+                    $visit;
                 }
             }
-            Self::UnorderedList { items } => {
-                for item in items.$iter_method().flat_map(|i| i.content.$iter_method()) {
-                    item.value.$visit_method($visitor);
+            WikitextSimplifiedNode::UnorderedList { items } => {
+                for $child in items.$iter_method().flat_map(|i| i.content.$iter_method()) {
+                    $visit;
                 }
             }
-            Self::DefinitionList { items } => {
+            WikitextSimplifiedNode::DefinitionList { items } => {
                 for item in items.$iter_method() {
-                    for child in item.content.$iter_method() {
-                        child.value.$visit_method($visitor);
+                    for $child in item.content.$iter_method() {
+                        $visit;
                     }
                 }
             }
-            Self::Template { .. }
-            | Self::Link { .. }
-            | Self::ExtLink { .. }
-            | Self::Text { .. }
-            | Self::Redirect { .. }
-            | Self::HorizontalDivider
-            | Self::ParagraphBreak
-            | Self::Newline => {}
+            WikitextSimplifiedNode::BehaviorSwitch { .. }
+            | WikitextSimplifiedNode::Category { .. }
+            | WikitextSimplifiedNode::Nowiki { .. }
+            | WikitextSimplifiedNode::Comment { .. }
+            | WikitextSimplifiedNode::Text { .. }
+            | WikitextSimplifiedNode::Redirect { .. }
+            | WikitextSimplifiedNode::HorizontalDivider
+            | WikitextSimplifiedNode::ParagraphBreak
+            | WikitextSimplifiedNode::Newline => {}
         }
     };
 }
+/// Configuration options for visiting nodes.
+#[derive(Debug, Default, Clone)]
+pub struct VisitConfig {
+    /// Whether to visit [`WikitextSimplifiedNode::Comment`] nodes.
+    ///
+    /// Comments are skipped by default, as most consumers are only interested in the content
+    /// of the page.
+    pub include_comments: bool,
+}
+impl VisitConfig {
+    fn should_visit(&self, node: &WikitextSimplifiedNode) -> bool {
+        self.include_comments || !matches!(node, WikitextSimplifiedNode::Comment { .. })
+    }
+}
+
 impl WikitextSimplifiedNode {
     /// Visits this node and all its children recursively with the given visitor function,
     /// including "deep" children in tables, lists, and more.
     ///
     /// The visitor function is called on each node in depth-first order, starting with
     /// this node and then visiting all its children.
+    ///
+    /// Helper function that calls [`Self::visit_with_config`] with the default configuration.
     pub fn visit(&self, visitor: &mut impl FnMut(&Self)) {
+        self.visit_with_config(&VisitConfig::default(), visitor);
+    }
+
+    /// Visits this node and all its children recursively with the given visitor function,
+    /// including "deep" children in tables, lists, and more.
+    ///
+    /// The visitor function is called on each node in depth-first order, starting with
+    /// this node and then visiting all its children.
+    pub fn visit_with_config(&self, config: &VisitConfig, visitor: &mut impl FnMut(&Self)) {
+        if !config.should_visit(self) {
+            return;
+        }
         visitor(self);
-        visit_children_impl!(self, visitor, visit, iter);
+        visit_children_impl!(self, iter, |child| child
+            .value
+            .visit_with_config(config, visitor));
     }
 
     /// Visits this node and all its children recursively with the given visitor function,
@@ -732,20 +1342,86 @@ impl WikitextSimplifiedNode {
     ///
     /// The visitor function is called on each node in depth-first order, starting with
     /// this node and then visiting all its children.
+    ///
+    /// Helper function that calls [`Self::visit_mut_with_config`] with the default configuration.
     pub fn visit_mut(&mut self, visitor: &mut impl FnMut(&mut Self)) {
+        self.visit_mut_with_config(&VisitConfig::default(), visitor);
+    }
+
+    /// Visits this node and all its children recursively with the given visitor function,
+    /// including "deep" children in tables, lists, and more.
+    ///
+    /// The visitor function is called on each node in depth-first order, starting with
+    /// this node and then visiting all its children.
+    pub fn visit_mut_with_config(
+        &mut self,
+        config: &VisitConfig,
+        visitor: &mut impl FnMut(&mut Self),
+    ) {
+        if !config.should_visit(self) {
+            return;
+        }
         visitor(self);
-        visit_children_impl!(self, visitor, visit_mut, iter_mut);
+        visit_children_impl!(self, iter_mut, |child| child
+            .value
+            .visit_mut_with_config(config, visitor));
     }
 
     /// Visits this node and all its children recursively with the given visitor function,
     /// replacing the node with the result of the visitor function.
     ///
     /// The visitor function is called on the children of each node first, and then on the node itself.
+    ///
+    /// Helper function that calls [`Self::visit_and_replace_mut_with_config`] with the default configuration.
     pub fn visit_and_replace_mut(&mut self, visitor: &mut impl FnMut(&Self) -> Self) {
-        visit_children_impl!(self, visitor, visit_and_replace_mut, iter_mut);
+        self.visit_and_replace_mut_with_config(&VisitConfig::default(), visitor);
+    }
+
+    /// Visits this node and all its children recursively with the given visitor function,
+    /// replacing the node with the result of the visitor function.
+    ///
+    /// The visitor function is called on the children of each node first, and then on the node itself.
+    /// Nodes that are not visited are left unchanged.
+    pub fn visit_and_replace_mut_with_config(
+        &mut self,
+        config: &VisitConfig,
+        visitor: &mut impl FnMut(&Self) -> Self,
+    ) {
+        if !config.should_visit(self) {
+            return;
+        }
+        visit_children_impl!(self, iter_mut, |child| child
+            .value
+            .visit_and_replace_mut_with_config(config, visitor));
         *self = visitor(self);
     }
 }
+impl Spanned<WikitextSimplifiedNode> {
+    /// Visits this node and all its children recursively with the given visitor function,
+    /// including "deep" children in tables, lists, and more.
+    ///
+    /// Unlike [`WikitextSimplifiedNode::visit`], the visitor receives each node together
+    /// with its span in the source text.
+    ///
+    /// Helper function that calls [`Self::visit_spanned_with_config`] with the default configuration.
+    pub fn visit_spanned(&self, visitor: &mut impl FnMut(&Self)) {
+        self.visit_spanned_with_config(&VisitConfig::default(), visitor);
+    }
+
+    /// Visits this node and all its children recursively with the given visitor function,
+    /// including "deep" children in tables, lists, and more.
+    ///
+    /// Unlike [`WikitextSimplifiedNode::visit_with_config`], the visitor receives each node
+    /// together with its span in the source text.
+    pub fn visit_spanned_with_config(&self, config: &VisitConfig, visitor: &mut impl FnMut(&Self)) {
+        if !config.should_visit(&self.value) {
+            return;
+        }
+        visitor(self);
+        visit_children_impl!(&self.value, iter, |child| child
+            .visit_spanned_with_config(config, visitor));
+    }
+}
 
 /// A parameter for a wikitext template
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -755,7 +1431,38 @@ pub struct TemplateParameter {
     /// The name of the parameter
     pub name: String,
     /// The value of the parameter
-    pub value: String,
+    pub value: Vec<Spanned<WikitextSimplifiedNode>>,
+    /// The value of the parameter as it appears in the source
+    pub raw_value: String,
+    /// The span of the whole parameter in the source, excluding the leading `|` but including
+    /// any surrounding whitespace
+    pub span: Span,
+    /// The span of the name of the parameter in the source, or `None` if it is positional
+    pub name_span: Option<Span>,
+    /// The span of the value of the parameter in the source.
+    ///
+    /// As with MediaWiki, this excludes surrounding whitespace for named parameters, but
+    /// includes it for positional parameters.
+    pub value_span: Span,
+}
+
+/// Configuration options for simplifying wikitext nodes.
+#[derive(Debug, Clone)]
+pub struct SimplificationConfig {
+    /// Whether to preserve HTML comments as [`WikitextSimplifiedNode::Comment`] nodes.
+    pub preserve_comments: bool,
+    /// The prefixes used to resolve link targets and template names.
+    ///
+    /// Defaults to [`wikitext_util::wikipedia_link_prefixes`].
+    pub link_prefixes: Cow<'static, LinkPrefixes>,
+}
+impl Default for SimplificationConfig {
+    fn default() -> Self {
+        Self {
+            preserve_comments: false,
+            link_prefixes: Cow::Borrowed(wikipedia_link_prefixes()),
+        }
+    }
 }
 
 /// Converts a sequence of raw wikitext nodes into simplified nodes.
@@ -763,6 +1470,8 @@ pub struct TemplateParameter {
 /// This function takes the original wikitext string and a sequence of nodes from
 /// [`parse_wiki_text_2`] and converts them into the simplified node structure.
 ///
+/// Helper function that calls [`simplify_wikitext_nodes_with_config`] with the default configuration.
+///
 /// # Errors
 ///
 /// This function will return an error if it encounters an unknown node type or if the stack
@@ -771,21 +1480,105 @@ pub fn simplify_wikitext_nodes(
     wikitext: &str,
     nodes: &[pwt::Node],
 ) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
-    use WikitextSimplifiedNode as WSN;
-    let mut root_stack = RootStack::new(wikitext);
+    simplify_wikitext_nodes_with_config(wikitext, nodes, &SimplificationConfig::default())
+}
 
-    // Awful hack to deal with templates: special-case single start/end tags and preserve them as texts
-    if nodes.len() == 1 {
-        match &nodes[0] {
-            pwt::Node::StartTag { start, end, .. } => {
-                return Ok(vec![Spanned {
-                    value: WSN::Text {
-                        text: nodes_wikitext(wikitext, nodes),
-                    },
-                    span: Span {
-                        start: *start,
-                        end: *end,
-                    },
+/// Converts a sequence of raw wikitext nodes into simplified nodes.
+///
+/// This function takes the original wikitext string and a sequence of nodes from
+/// [`parse_wiki_text_2`] and converts them into the simplified node structure.
+///
+/// # Errors
+///
+/// This function will return an error if it encounters an unknown node type or if the stack
+/// of nodes is not properly closed.
+pub fn simplify_wikitext_nodes_with_config(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    config: &SimplificationConfig,
+) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+    simplify_wikitext_nodes_with_diagnostics(wikitext, nodes, config).map(|(nodes, _)| nodes)
+}
+
+/// Converts a sequence of raw wikitext nodes into simplified nodes, returning any
+/// [`Diagnostic`]s reported along the way.
+///
+/// # Errors
+///
+/// This function will return an error if it encounters an unknown node type or if the stack
+/// of nodes is not properly closed.
+pub fn simplify_wikitext_nodes_with_diagnostics(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    config: &SimplificationConfig,
+) -> Result<(Vec<Spanned<WikitextSimplifiedNode>>, Vec<Diagnostic>), SimplificationError> {
+    let mut diagnostics = DiagnosticCollector::default();
+    let mut nodes = simplify_wikitext_nodes_impl(wikitext, nodes, config, &mut diagnostics)?;
+    autolink_bare_urls(wikitext, &mut nodes, &config.link_prefixes);
+    Ok((nodes, diagnostics.diagnostics))
+}
+
+/// Converts a sequence of raw wikitext nodes into simplified nodes, recovering from
+/// malformed wikitext instead of returning an error.
+///
+/// Nodes that cannot be simplified are kept as text, and each recovery from malformed
+/// markup is reported as a [`Diagnostic`]: tags left open are closed at the end of the
+/// nodes, formatting left open is closed at the end of its line, formatting closed by the
+/// closing tag of an element it was opened within is reopened after it, and closing tags
+/// without a matching open tag are discarded. Wikitext that can be simplified without
+/// recovering is simplified as with [`simplify_wikitext_nodes_with_diagnostics`], which
+/// handles malformed markup in the same way without reporting it.
+pub fn simplify_wikitext_nodes_recovering(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    config: &SimplificationConfig,
+) -> (Vec<Spanned<WikitextSimplifiedNode>>, Vec<Diagnostic>) {
+    let mut diagnostics = DiagnosticCollector::recovering();
+    let mut nodes = match simplify_wikitext_nodes_impl(wikitext, nodes, config, &mut diagnostics) {
+        Ok(nodes) => nodes,
+        // Every error is recovered from, but keep the source rather than panicking if one
+        // is missed
+        Err(error) => {
+            let span = nodes_span(nodes).unwrap_or(Span { start: 0, end: 0 });
+            diagnostics.push(Diagnostic {
+                severity: DiagnosticSeverity::Error,
+                kind: DiagnosticKind::UnknownNode,
+                span,
+                message: format!("Wikitext could not be simplified: {error}"),
+            });
+            vec![Spanned {
+                value: WikitextSimplifiedNode::Text {
+                    text: wikitext[span.start..span.end].to_string(),
+                },
+                span,
+            }]
+        }
+    };
+    autolink_bare_urls(wikitext, &mut nodes, &config.link_prefixes);
+    (nodes, diagnostics.diagnostics)
+}
+
+fn simplify_wikitext_nodes_impl(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    config: &SimplificationConfig,
+    diagnostics: &mut DiagnosticCollector,
+) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+    use WikitextSimplifiedNode as WSN;
+    let mut root_stack = RootStack::new(wikitext);
+
+    // Awful hack to deal with templates: special-case single start/end tags and preserve them as texts
+    if nodes.len() == 1 {
+        match &nodes[0] {
+            pwt::Node::StartTag { start, end, .. } => {
+                return Ok(vec![Spanned {
+                    value: WSN::Text {
+                        text: nodes_wikitext(wikitext, nodes),
+                    },
+                    span: Span {
+                        start: *start,
+                        end: *end,
+                    },
                 }]);
             }
             pwt::Node::EndTag { start, end, .. } => {
@@ -807,136 +1600,116 @@ pub fn simplify_wikitext_nodes(
     /// not be considered for stack-based tag closure matching.
     const FAKE_TAGS: [&str; 4] = ["br/", "hr/", "br", "hr"];
 
+    let quotes = QuoteResolution::new(wikitext, nodes);
     let mut text_start_override = None;
     for node in nodes {
         root_stack.set_current_node(node);
+        // Formatting ends at the end of its line, once any tags opened within it are closed
+        root_stack.close_ended_formatting(
+            NodeMetadata::for_node(node).start,
+            &quotes,
+            diagnostics,
+        )?;
         match node {
-            pwt::Node::Bold { start, end } => {
-                if matches!(root_stack.last_layer(), WSN::Bold { .. }) {
-                    let bold = root_stack.pop_layer(*end)?;
-                    root_stack.add_to_children(bold)?;
-                } else {
-                    root_stack.push_layer(WSN::Bold { children: vec![] }, *start);
-                }
-            }
-            pwt::Node::Italic { start, end } => {
-                if matches!(root_stack.last_layer(), WSN::Italic { .. }) {
-                    let italic = root_stack.pop_layer(*end)?;
-                    root_stack.add_to_children(italic)?;
-                } else {
-                    root_stack.push_layer(WSN::Italic { children: vec![] }, *start);
+            pwt::Node::Bold { start, end }
+            | pwt::Node::Italic { start, end }
+            | pwt::Node::BoldItalic { start, end } => {
+                // Tokens after the first in a run of apostrophes were resolved with it
+                let Some(run) = quotes.run_at(*start) else {
+                    continue;
+                };
+                let markup = run.markup_span();
+                if run.literal > 0 {
+                    root_stack.add_text(Span {
+                        start: run.span.start,
+                        end: markup.start,
+                    })?;
                 }
-            }
-            pwt::Node::BoldItalic { start, end } => {
-                if matches!(root_stack.last_layer(), WSN::Italic { .. }) {
-                    let italic = root_stack.pop_layer(*end)?;
-                    if matches!(root_stack.last_layer(), WSN::Bold { .. }) {
-                        let mut bold = root_stack.pop_layer(*end)?;
-                        bold.value.children_mut().unwrap().push(italic);
-                        root_stack.add_to_children(bold)?;
-                    } else {
-                        return Err(SimplificationError::InvalidNodeStructure {
-                            kind: NodeStructureError::MissingBoldLayer,
-                            context: SimplificationErrorContext {
-                                content: wikitext[*start..*end].to_string(),
-                                start: *start,
-                                end: *end,
-                            },
-                        });
+                for action in &run.actions {
+                    match action {
+                        QuoteAction::Open(quote) => {
+                            root_stack.push_layer(quote.node(), markup.start)
+                        }
+                        QuoteAction::Close(quote) => {
+                            root_stack.close_formatting(*quote, markup, diagnostics)?;
+                        }
                     }
-                } else {
-                    root_stack.push_layer(WSN::Bold { children: vec![] }, *start);
-                    root_stack.push_layer(WSN::Italic { children: vec![] }, *start);
+                }
+                if run.reinterpreted {
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Warning,
+                        kind: DiagnosticKind::UnbalancedQuotes,
+                        span: run.span,
+                        message: "The line has an odd number of both bold and italic \
+                                  apostrophes, so this bold was treated as an apostrophe \
+                                  followed by italics"
+                            .into(),
+                    });
+                }
+                if run.reopens_formatting() {
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Warning,
+                        kind: DiagnosticKind::MisnestedFormatting,
+                        span: markup,
+                        message: "Bold and italics were closed in an order that could not be \
+                                  nested, and were closed and reopened to match"
+                            .into(),
+                    });
+                }
+                // The rest of the run may have been parsed as text
+                if run.span.end > *end {
+                    text_start_override = Some(run.span.end);
                 }
             }
+            pwt::Node::EndTag { name, start, end } if !FAKE_TAGS.contains(&name.as_ref()) => {
+                root_stack.close_tag(
+                    name,
+                    Span {
+                        start: *start,
+                        end: *end,
+                    },
+                    diagnostics,
+                )?;
+            }
             pwt::Node::StartTag { name, start, .. } if name == "blockquote" => {
                 root_stack.push_layer(WSN::Blockquote { children: vec![] }, *start);
             }
-            pwt::Node::EndTag { name, end, start } if name == "blockquote" => {
-                let blockquote = root_stack.pop_layer(*end)?;
-                assert_tag_closure_matches(wikitext, name, "blockquote", *start, *end)?;
-                root_stack.add_to_children(blockquote)?;
-            }
             pwt::Node::StartTag { name, start, .. } if name == "sup" => {
                 root_stack.push_layer(WSN::Superscript { children: vec![] }, *start);
             }
-            pwt::Node::EndTag { name, end, start } if name == "sup" => {
-                let superscript = root_stack.pop_layer(*end)?;
-                assert_tag_closure_matches(wikitext, name, "sup", *start, *end)?;
-                root_stack.add_to_children(superscript)?;
-            }
             pwt::Node::StartTag { name, start, .. } if name == "sub" => {
                 root_stack.push_layer(WSN::Subscript { children: vec![] }, *start);
             }
-            pwt::Node::EndTag { name, end, start } if name == "sub" => {
-                let subscript = root_stack.pop_layer(*end)?;
-                assert_tag_closure_matches(wikitext, name, "sub", *start, *end)?;
-                root_stack.add_to_children(subscript)?;
-            }
             pwt::Node::StartTag { name, start, .. } if name == "small" => {
                 root_stack.push_layer(WSN::Small { children: vec![] }, *start);
             }
-            pwt::Node::EndTag { name, end, start } if name == "small" => {
-                let small = root_stack.pop_layer(*end)?;
-                assert_tag_closure_matches(wikitext, name, "small", *start, *end)?;
-                root_stack.add_to_children(small)?;
-            }
             pwt::Node::StartTag { name, start, .. } if name == "pre" => {
                 root_stack.push_layer(WSN::Preformatted { children: vec![] }, *start);
             }
-            pwt::Node::EndTag { name, end, start } if name == "pre" => {
-                let preformatted = root_stack.pop_layer(*end)?;
-                assert_tag_closure_matches(wikitext, name, "pre", *start, *end)?;
-                root_stack.add_to_children(preformatted)?;
-            }
             pwt::Node::StartTag {
                 name, start, end, ..
             } if !FAKE_TAGS.contains(&name.as_ref()) => {
                 // Extract attributes from the tag content, e.g. <div class="foo"> -> class="foo"
-                let tag_content = &wikitext[*start..*end];
-                let closing_bracket_pos = tag_content.find('>').unwrap_or(tag_content.len());
-                let opening_tag = &tag_content[..closing_bracket_pos];
+                let (attributes, raw_attributes) = extract_tag_attributes(wikitext, *start, *end);
 
                 root_stack.push_layer(
                     WSN::Tag {
                         name: name.to_string(),
-                        attributes: extract_tag_attributes(opening_tag),
+                        attributes,
+                        raw_attributes,
                         children: vec![],
                     },
                     *start,
                 );
             }
-            pwt::Node::EndTag { name, start, end } if !FAKE_TAGS.contains(&name.as_ref()) => {
-                // Check if there's a matching opening tag on the stack
-                if root_stack.find_matching_tag(name).is_some() {
-                    let tag = root_stack.pop_layer(*end)?;
-                    if let WSN::Tag { name: tag_name, .. } = &tag.value {
-                        assert_tag_closure_matches(wikitext, name, tag_name, *start, *end)?;
-                    } else {
-                        // This shouldn't happen if find_matching_tag works correctly,
-                        // but handle it gracefully by treating as orphan
-                        root_stack.push_layer_back(tag);
-                        root_stack.add_to_children(Spanned {
-                            value: WSN::Text {
-                                text: wikitext[*start..*end].to_string(),
-                            },
-                            span: Span {
-                                start: *start,
-                                end: *end,
-                            },
-                        })?;
-                        continue;
-                    }
-                    root_stack.add_to_children(tag)?;
-                } else {
-                    // No matching opening tag found - silently discard the orphan closing tag
-                    // (this matches MediaWiki's behavior)
-                }
-            }
             other => {
-                if let Some(simplified_node) =
-                    simplify_wikitext_node(wikitext, other, text_start_override)?
-                {
+                if let Some(simplified_node) = simplify_wikitext_node_impl(
+                    wikitext,
+                    other,
+                    text_start_override,
+                    config,
+                    diagnostics,
+                )? {
                     // HACK: deal with `link_trail` by preserving the end of the link and forcing the next
                     // text to start at the end of the link
                     text_start_override = match &simplified_node.value {
@@ -944,35 +1717,18 @@ pub fn simplify_wikitext_nodes(
                         _ => None,
                     };
 
-                    root_stack.add_to_children(simplified_node)?;
+                    root_stack.add_to_children_within_line(
+                        simplified_node,
+                        &quotes,
+                        diagnostics,
+                    )?;
                 }
             }
         }
     }
 
-    fn assert_tag_closure_matches(
-        wikitext: &str,
-        end_tag_name: &str,
-        last_node_name: &str,
-        start: usize,
-        end: usize,
-    ) -> Result<(), SimplificationError> {
-        if last_node_name == end_tag_name {
-            return Ok(());
-        }
-        Err(SimplificationError::InvalidNodeStructure {
-            kind: NodeStructureError::TagClosureMismatch {
-                expected: end_tag_name.to_string(),
-                actual: last_node_name.to_string(),
-            },
-            context: SimplificationErrorContext {
-                content: wikitext[start..end].to_string(),
-                start,
-                end,
-            },
-        })
-    }
-    root_stack.unwind()
+    root_stack.close_ended_formatting(usize::MAX, &quotes, diagnostics)?;
+    root_stack.unwind(diagnostics)
 }
 
 /// Converts a single raw wikitext node into a simplified node.
@@ -981,6 +1737,8 @@ pub fn simplify_wikitext_nodes(
 /// format into the simplified format. It handles various node types including templates,
 /// links, text, and formatting nodes.
 ///
+/// Helper function that calls [`simplify_wikitext_node_with_config`] with the default configuration.
+///
 /// # Errors
 ///
 /// This function will return an error if it encounters an unknown node type.
@@ -988,6 +1746,49 @@ pub fn simplify_wikitext_node(
     wikitext: &str,
     node: &pwt::Node,
     text_start_override: Option<usize>,
+) -> Result<Option<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+    simplify_wikitext_node_with_config(
+        wikitext,
+        node,
+        text_start_override,
+        &SimplificationConfig::default(),
+    )
+}
+
+/// Converts a single raw wikitext node into a simplified node.
+///
+/// This function handles the conversion of individual nodes from the [`parse_wiki_text_2`]
+/// format into the simplified format. It handles various node types including templates,
+/// links, text, and formatting nodes.
+///
+/// # Errors
+///
+/// This function will return an error if it encounters an unknown node type.
+pub fn simplify_wikitext_node_with_config(
+    wikitext: &str,
+    node: &pwt::Node,
+    text_start_override: Option<usize>,
+    config: &SimplificationConfig,
+) -> Result<Option<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+    let mut node = simplify_wikitext_node_impl(
+        wikitext,
+        node,
+        text_start_override,
+        config,
+        &mut DiagnosticCollector::default(),
+    )?;
+    if let Some(node) = &mut node {
+        autolink_bare_urls_in_node(wikitext, node, &config.link_prefixes);
+    }
+    Ok(node)
+}
+
+fn simplify_wikitext_node_impl(
+    wikitext: &str,
+    node: &pwt::Node,
+    text_start_override: Option<usize>,
+    config: &SimplificationConfig,
+    diagnostics: &mut DiagnosticCollector,
 ) -> Result<Option<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
     use WikitextSimplifiedNode as WSN;
     match node {
@@ -997,35 +1798,107 @@ pub fn simplify_wikitext_node(
             start,
             end,
         } => {
+            let is_whitespace = |c: char| matches!(c, ' ' | '\t' | '\n');
+
             let mut unnamed_parameter_index = 1;
-            let mut new_parameters = vec![];
+            let mut new_parameters: Vec<TemplateParameter> = vec![];
             for parameter in parameters {
                 let name = if let Some(parameter_name) = &parameter.name {
-                    nodes_inner_text(parameter_name)
+                    nodes_inner_text(parameter_name).trim().to_string()
                 } else {
                     let name = unnamed_parameter_index.to_string();
                     unnamed_parameter_index += 1;
                     name
                 };
 
-                let value_start = parameter
-                    .value
-                    .first()
-                    .map(|v| NodeMetadata::for_node(v).start)
-                    .unwrap_or_default();
-                let value_end = parameter
-                    .value
-                    .last()
-                    .map(|v| NodeMetadata::for_node(v).end)
-                    .unwrap_or_default();
-                let value = wikitext[value_start..value_end].to_string();
+                // `parse_wiki_text_2` excludes surrounding whitespace from the parameter, so
+                // recover the full extent of the parameter between its separators
+                let span = Span {
+                    start: wikitext[..parameter.start]
+                        .trim_end_matches(is_whitespace)
+                        .len(),
+                    end: wikitext.len()
+                        - wikitext[parameter.end..]
+                            .trim_start_matches(is_whitespace)
+                            .len(),
+                };
+                let trimmed_value_span = nodes_span(&parameter.value).unwrap_or(Span {
+                    start: parameter.end.max(span.start),
+                    end: parameter.end.max(span.start),
+                });
+                // As with MediaWiki, named parameter values are trimmed, but positional
+                // parameter values are not
+                let value_span = if parameter.name.is_some() {
+                    trimmed_value_span
+                } else {
+                    span
+                };
+                let raw_value = wikitext[value_span.start..value_span.end].to_string();
+
+                // Parameter values are not required to be well-formed on their own (e.g. a
+                // parameter that opens a tag that the template closes), so fall back to the
+                // raw text if they can't be simplified
+                let value = match diagnostics.without_recovery(|diagnostics| {
+                    simplify_wikitext_nodes_impl(wikitext, &parameter.value, config, diagnostics)
+                }) {
+                    Ok(mut value) => {
+                        pad_with_source_text(wikitext, &mut value, value_span, trimmed_value_span);
+                        value
+                    }
+                    Err(error) => {
+                        diagnostics.push(Diagnostic {
+                            severity: DiagnosticSeverity::Warning,
+                            kind: DiagnosticKind::UnsimplifiableTemplateParameter,
+                            span: value_span,
+                            message: format!(
+                                "Value of parameter `{name}` could not be simplified: {error}"
+                            ),
+                        });
+                        vec![Spanned {
+                            value: WSN::Text {
+                                text: raw_value.clone(),
+                            },
+                            span: value_span,
+                        }]
+                    }
+                };
+
+                // As with MediaWiki, later parameters override earlier ones with the same name,
+                // including explicitly numbered parameters overriding positional ones
+                if let Some(index) = new_parameters.iter().position(|p| p.name == name) {
+                    let overridden = new_parameters.remove(index);
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Warning,
+                        kind: DiagnosticKind::DuplicateTemplateParameter,
+                        span: overridden.span,
+                        message: format!(
+                            "Parameter `{name}` is overridden by a later parameter with the same name"
+                        ),
+                    });
+                }
 
-                new_parameters.push(TemplateParameter { name, value });
+                new_parameters.push(TemplateParameter {
+                    name,
+                    value,
+                    raw_value,
+                    span,
+                    name_span: parameter.name.as_deref().and_then(nodes_span),
+                    value_span,
+                });
             }
 
+            let name_text = nodes_inner_text(name);
             return Ok(Some(Spanned {
                 value: WSN::Template {
-                    name: nodes_inner_text(name),
+                    normalized_name: TemplateName::parse_with_prefixes(
+                        &name_text,
+                        &config.link_prefixes,
+                    ),
+                    name: name_text,
+                    name_span: nodes_span(name).unwrap_or(Span {
+                        start: *start + 2,
+                        end: *start + 2,
+                    }),
                     parameters: new_parameters,
                 },
                 span: Span {
@@ -1034,9 +1907,16 @@ pub fn simplify_wikitext_node(
                 },
             }));
         }
-        pwt::Node::MagicWord { .. } => {
-            // Making the current assumption that we don't care about these
-            return Ok(None);
+        pwt::Node::MagicWord { start, end } => {
+            return Ok(Some(Spanned {
+                value: WSN::BehaviorSwitch {
+                    name: wikitext[*start..*end].trim_matches('_').to_uppercase(),
+                },
+                span: Span {
+                    start: *start,
+                    end: *end,
+                },
+            }));
         }
         pwt::Node::Heading {
             level,
@@ -1047,7 +1927,7 @@ pub fn simplify_wikitext_node(
             return Ok(Some(Spanned {
                 value: WSN::Heading {
                     level: *level,
-                    children: simplify_wikitext_nodes(wikitext, nodes)?,
+                    children: simplify_wikitext_nodes_impl(wikitext, nodes, config, diagnostics)?,
                 },
                 span: Span {
                     start: *start,
@@ -1067,8 +1947,9 @@ pub fn simplify_wikitext_node(
         } => {
             return Ok(Some(Spanned {
                 value: WSN::Link {
-                    text: nodes_wikitext(wikitext, text),
+                    text: simplify_wikitext_nodes_impl(wikitext, text, config, diagnostics)?,
                     title: target.to_string(),
+                    target: LinkTarget::parse_with_prefixes(target, &config.link_prefixes),
                 },
                 span: Span {
                     start: *start,
@@ -1077,15 +1958,66 @@ pub fn simplify_wikitext_node(
             }));
         }
         pwt::Node::ExternalLink { nodes, start, end } => {
-            let inner = nodes_wikitext(wikitext, nodes);
-            let (link, text) = inner
-                .split_once(' ')
-                .map(|(l, t)| (l, Some(t)))
-                .unwrap_or((&inner, None));
+            let is_whitespace = |c: char| matches!(c, ' ' | '\t');
+
+            // The URL continues up to the first whitespace and may contain templates, but any
+            // other markup (e.g. formatting or tags) starts the label
+            let label_index = nodes.iter().position(|node| match node {
+                pwt::Node::Text { value, .. } => value.contains(is_whitespace),
+                pwt::Node::Template { .. }
+                | pwt::Node::Parameter { .. }
+                | pwt::Node::CharacterEntity { .. } => false,
+                _ => true,
+            });
+            let url_end = match label_index.map(|index| &nodes[index]) {
+                Some(pwt::Node::Text { value, start, .. }) => {
+                    start + value.find(is_whitespace).unwrap_or(value.len())
+                }
+                Some(node) => NodeMetadata::for_node(node).start,
+                None => end.saturating_sub(1).max(start + 1),
+            };
+            let link = &wikitext[start + 1..url_end];
+
+            let text = match label_index {
+                Some(index) => {
+                    let mut text = vec![];
+                    let mut rest = &nodes[index..];
+                    if let Some(pwt::Node::Text { value, start, end }) = rest.first() {
+                        let label = value[url_end - start..].trim_start_matches(is_whitespace);
+                        if !label.is_empty() {
+                            text.push(Spanned {
+                                value: WSN::Text {
+                                    text: label.to_string(),
+                                },
+                                span: Span {
+                                    start: end - label.len(),
+                                    end: *end,
+                                },
+                            });
+                        }
+                        rest = &rest[1..];
+                    }
+                    text.extend(simplify_wikitext_nodes_impl(
+                        wikitext,
+                        rest,
+                        config,
+                        diagnostics,
+                    )?);
+                    Some(text).filter(|text| !text.is_empty())
+                }
+                None => None,
+            };
+
             return Ok(Some(Spanned {
                 value: WSN::ExtLink {
                     link: link.to_string(),
-                    text: text.map(|s| s.to_string()),
+                    protocol: config
+                        .link_prefixes
+                        .protocol(link)
+                        .unwrap_or_default()
+                        .to_string(),
+                    text,
+                    bracketed: true,
                 },
                 span: Span {
                     start: *start,
@@ -1096,7 +2028,7 @@ pub fn simplify_wikitext_node(
         pwt::Node::Text { value, start, end } => {
             let text_start = text_start_override.unwrap_or(*start);
             let text_start_offset = text_start.saturating_sub(*start);
-            let text = &value[text_start_offset..];
+            let text = &value[text_start_offset.min(value.len())..];
             if text.is_empty() {
                 return Ok(None);
             }
@@ -1134,9 +2066,98 @@ pub fn simplify_wikitext_node(
                 },
             }));
         }
-        pwt::Node::Category { .. } | pwt::Node::Comment { .. } | pwt::Node::Image { .. } => {
-            // Don't care
-            return Ok(None);
+        pwt::Node::Comment { start, end } => {
+            if !config.preserve_comments {
+                return Ok(None);
+            }
+            // Unterminated comments run until the end of the document, so the closing
+            // marker may be missing
+            let comment = &wikitext[*start..*end];
+            let comment = comment.strip_prefix("<!--").unwrap_or(comment);
+            let comment = comment.strip_suffix("-->").unwrap_or(comment);
+            return Ok(Some(Spanned {
+                value: WSN::Comment {
+                    text: comment.to_string(),
+                },
+                span: Span {
+                    start: *start,
+                    end: *end,
+                },
+            }));
+        }
+        pwt::Node::Category {
+            target,
+            ordinal,
+            start,
+            end,
+        } => {
+            // Strip the namespace, which may be any of the configured category namespaces
+            let name = target
+                .split_once(':')
+                .map(|(_, name)| name)
+                .unwrap_or(target)
+                .trim();
+            let sort_key = nodes_wikitext(wikitext, ordinal);
+            return Ok(Some(Spanned {
+                value: WSN::Category {
+                    name: name.to_string(),
+                    sort_key: (!sort_key.is_empty()).then_some(sort_key),
+                },
+                span: Span {
+                    start: *start,
+                    end: *end,
+                },
+            }));
+        }
+        pwt::Node::Image {
+            target,
+            text,
+            start,
+            end,
+        } => {
+            // The options and caption are not parsed by `parse_wiki_text_2`, so we split
+            // the source on top-level pipes ourselves; the first segment is the target.
+            let inner_start = (*start + 2).min(*end);
+            let inner_end = if wikitext[inner_start..*end].ends_with("]]") {
+                *end - 2
+            } else {
+                *end
+            };
+
+            let mut options = ImageOptions::default();
+            let mut caption = None;
+            for segment in split_top_level_pipes(wikitext, inner_start, inner_end)
+                .into_iter()
+                .skip(1)
+            {
+                if !options.apply(&wikitext[segment.start..segment.end]) {
+                    // As with MediaWiki, the last unrecognised option is the caption
+                    caption = Some(segment);
+                }
+            }
+
+            return Ok(Some(Spanned {
+                value: WSN::Image {
+                    file: target.to_string(),
+                    options,
+                    caption: caption
+                        .map(|range| {
+                            simplify_wikitext_nodes_in_range(
+                                wikitext,
+                                text,
+                                range,
+                                config,
+                                diagnostics,
+                            )
+                        })
+                        .transpose()?
+                        .unwrap_or_default(),
+                },
+                span: Span {
+                    start: *start,
+                    end: *end,
+                },
+            }));
         }
         pwt::Node::Table {
             attributes,
@@ -1148,12 +2169,15 @@ pub fn simplify_wikitext_node(
             // Convert captions
             let mut simplified_captions = vec![];
             for caption in captions {
-                let caption_content = simplify_wikitext_nodes(wikitext, &caption.content)?;
+                let caption_content =
+                    simplify_wikitext_nodes_impl(wikitext, &caption.content, config, diagnostics)?;
                 simplified_captions.push(WikitextSimplifiedTableCaption {
                     attributes: caption
                         .attributes
                         .as_deref()
-                        .map(|attrs| simplify_wikitext_nodes(wikitext, attrs))
+                        .map(|attrs| {
+                            simplify_wikitext_nodes_impl(wikitext, attrs, config, diagnostics)
+                        })
                         .transpose()?,
                     content: caption_content,
                 });
@@ -1164,27 +2188,40 @@ pub fn simplify_wikitext_node(
             for row in rows {
                 let mut cells = vec![];
                 for cell in &row.cells {
-                    let cell_content = simplify_wikitext_nodes(wikitext, &cell.content)?;
+                    let cell_content =
+                        simplify_wikitext_nodes_impl(wikitext, &cell.content, config, diagnostics)?;
                     cells.push(WikitextSimplifiedTableCell {
                         is_header: cell.type_ == pwt::TableCellType::Heading,
                         attributes: cell
                             .attributes
                             .as_deref()
-                            .map(|attrs| simplify_wikitext_nodes(wikitext, attrs))
+                            .map(|attrs| {
+                                simplify_wikitext_nodes_impl(wikitext, attrs, config, diagnostics)
+                            })
                             .transpose()?,
                         content: cell_content,
                     });
                 }
 
                 simplified_rows.push(WikitextSimplifiedTableRow {
-                    attributes: simplify_wikitext_nodes(wikitext, &row.attributes)?,
+                    attributes: simplify_wikitext_nodes_impl(
+                        wikitext,
+                        &row.attributes,
+                        config,
+                        diagnostics,
+                    )?,
                     cells,
                 });
             }
 
             return Ok(Some(Spanned {
                 value: WSN::Table {
-                    attributes: simplify_wikitext_nodes(wikitext, attributes)?,
+                    attributes: simplify_wikitext_nodes_impl(
+                        wikitext,
+                        attributes,
+                        config,
+                        diagnostics,
+                    )?,
                     captions: simplified_captions,
                     rows: simplified_rows,
                 },
@@ -1197,8 +2234,12 @@ pub fn simplify_wikitext_node(
         pwt::Node::OrderedList { items, start, end } => {
             let mut simplified_items = vec![];
             for item in items {
-                let content = simplify_wikitext_nodes(wikitext, &item.nodes)?;
-                simplified_items.push(WikitextSimplifiedListItem { content });
+                let content =
+                    simplify_wikitext_nodes_impl(wikitext, &item.nodes, config, diagnostics)?;
+                simplified_items.push(WikitextSimplifiedListItem {
+                    marker: list_item_marker(wikitext, item.start, &content),
+                    content,
+                });
             }
             return Ok(Some(Spanned {
                 value: WSN::OrderedList {
@@ -1213,8 +2254,12 @@ pub fn simplify_wikitext_node(
         pwt::Node::UnorderedList { items, start, end } => {
             let mut simplified_items = vec![];
             for item in items {
-                let content = simplify_wikitext_nodes(wikitext, &item.nodes)?;
-                simplified_items.push(WikitextSimplifiedListItem { content });
+                let content =
+                    simplify_wikitext_nodes_impl(wikitext, &item.nodes, config, diagnostics)?;
+                simplified_items.push(WikitextSimplifiedListItem {
+                    marker: list_item_marker(wikitext, item.start, &content),
+                    content,
+                });
             }
             return Ok(Some(Spanned {
                 value: WSN::UnorderedList {
@@ -1229,12 +2274,14 @@ pub fn simplify_wikitext_node(
         pwt::Node::DefinitionList { items, start, end } => {
             let mut simplified_items = vec![];
             for item in items {
-                let content = simplify_wikitext_nodes(wikitext, &item.nodes)?;
+                let content =
+                    simplify_wikitext_nodes_impl(wikitext, &item.nodes, config, diagnostics)?;
                 simplified_items.push(WikitextSimplifiedDefinitionListItem {
                     type_: match item.type_ {
                         pwt::DefinitionListItemType::Term => DefinitionListItemType::Term,
                         pwt::DefinitionListItemType::Details => DefinitionListItemType::Details,
                     },
+                    marker: list_item_marker(wikitext, item.start, &content),
                     content,
                 });
             }
@@ -1254,21 +2301,82 @@ pub fn simplify_wikitext_node(
             start,
             end,
         } => {
-            // Special handling for ref tags - ignore them
-            if name == "ref" || name == "references" || name == "gallery" || name == "nowiki" {
-                return Ok(None);
+            if name == "nowiki" {
+                // Take the content from the source, as it is not meant to be interpreted.
+                // A self-closing `<nowiki/>` has no content.
+                let tag_content = &wikitext[*start..*end];
+                let text = match (tag_content.find('>'), tag_content.rfind("</")) {
+                    (Some(open_end), Some(close_start)) if open_end < close_start => {
+                        &tag_content[open_end + 1..close_start]
+                    }
+                    _ => "",
+                };
+                return Ok(Some(Spanned {
+                    value: WSN::Nowiki {
+                        text: text.to_string(),
+                    },
+                    span: Span {
+                        start: *start,
+                        end: *end,
+                    },
+                }));
             }
 
             // Extract attributes from the opening tag content
-            let tag_content = &wikitext[*start..*end];
-            let closing_bracket_pos = tag_content.find('>').unwrap_or(tag_content.len());
-            let opening_tag = &tag_content[..closing_bracket_pos];
+            let (attributes, raw_attributes) = extract_tag_attributes(wikitext, *start, *end);
+
+            if name == "gallery" {
+                return Ok(Some(Spanned {
+                    value: WSN::Gallery {
+                        attributes,
+                        raw_attributes,
+                        entries: simplify_gallery_entries(
+                            wikitext,
+                            nodes,
+                            *start,
+                            *end,
+                            config,
+                            diagnostics,
+                        )?,
+                    },
+                    span: Span {
+                        start: *start,
+                        end: *end,
+                    },
+                }));
+            }
+
+            if name == "ref" || name == "references" {
+                let attribute = |key: &str| {
+                    find_tag_attribute(&attributes, key)
+                        .and_then(|attribute| attribute.value.clone())
+                        .filter(|value| !value.is_empty())
+                };
+                let group = attribute("group");
+                let children = simplify_wikitext_nodes_impl(wikitext, nodes, config, diagnostics)?;
+                return Ok(Some(Spanned {
+                    value: if name == "ref" {
+                        WSN::Reference {
+                            name: attribute("name"),
+                            group,
+                            children,
+                        }
+                    } else {
+                        WSN::ReferenceList { group, children }
+                    },
+                    span: Span {
+                        start: *start,
+                        end: *end,
+                    },
+                }));
+            }
 
             return Ok(Some(Spanned {
                 value: WSN::Tag {
                     name: name.to_string(),
-                    attributes: extract_tag_attributes(opening_tag),
-                    children: simplify_wikitext_nodes(wikitext, nodes)?,
+                    attributes,
+                    raw_attributes,
+                    children: simplify_wikitext_nodes_impl(wikitext, nodes, config, diagnostics)?,
                 },
                 span: Span {
                     start: *start,
@@ -1279,7 +2387,7 @@ pub fn simplify_wikitext_node(
         pwt::Node::Preformatted { nodes, start, end } => {
             return Ok(Some(Spanned {
                 value: WSN::Preformatted {
-                    children: simplify_wikitext_nodes(wikitext, nodes)?,
+                    children: simplify_wikitext_nodes_impl(wikitext, nodes, config, diagnostics)?,
                 },
                 span: Span {
                     start: *start,
@@ -1298,7 +2406,9 @@ pub fn simplify_wikitext_node(
                     name: nodes_inner_text(name),
                     default: default
                         .as_deref()
-                        .map(|nodes| simplify_wikitext_nodes(wikitext, nodes))
+                        .map(|nodes| {
+                            simplify_wikitext_nodes_impl(wikitext, nodes, config, diagnostics)
+                        })
                         .transpose()?,
                 },
                 span: Span {
@@ -1348,16 +2458,472 @@ pub fn simplify_wikitext_node(
         _ => {}
     }
     let metadata = NodeMetadata::for_node(node);
+    if diagnostics.recover {
+        let span = Span {
+            start: metadata.start,
+            end: metadata.end,
+        };
+        diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            kind: DiagnosticKind::UnknownNode,
+            span,
+            message: format!(
+                "Node of type '{:?}' could not be simplified, and was kept as text",
+                metadata.ty
+            ),
+        });
+        return Ok(Some(Spanned {
+            value: WSN::Text {
+                text: wikitext[span.start..span.end].to_string(),
+            },
+            span,
+        }));
+    }
     Err(SimplificationError::UnknownNode {
         node_type: metadata.ty,
         context: SimplificationErrorContext::from_node_metadata(wikitext, &metadata),
     })
 }
 
+/// Simplifies the nodes that lie within `range`, clipping any text nodes that straddle its
+/// boundaries.
+///
+/// This is used for constructs like image captions, where `parse_wiki_text_2` gives us a
+/// single run of nodes that also contains the `|`-separated options.
+fn simplify_wikitext_nodes_in_range(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    range: Span,
+    config: &SimplificationConfig,
+    diagnostics: &mut DiagnosticCollector,
+) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+    let is_inside = |node: &pwt::Node| {
+        let metadata = NodeMetadata::for_node(node);
+        metadata.start >= range.start && metadata.end <= range.end
+    };
+
+    let mut result = vec![];
+    let mut index = 0;
+    while index < nodes.len() {
+        if is_inside(&nodes[index]) {
+            // Simplify contiguous runs together so that formatting can span multiple nodes
+            let run_start = index;
+            while index < nodes.len() && is_inside(&nodes[index]) {
+                index += 1;
+            }
+            result.extend(simplify_wikitext_nodes_impl(
+                wikitext,
+                &nodes[run_start..index],
+                config,
+                diagnostics,
+            )?);
+            continue;
+        }
+
+        if let pwt::Node::Text { start, end, .. } = &nodes[index] {
+            let start = (*start).max(range.start);
+            let end = (*end).min(range.end);
+            if start < end {
+                result.push(Spanned {
+                    value: WikitextSimplifiedNode::Text {
+                        text: wikitext[start..end].to_string(),
+                    },
+                    span: Span { start, end },
+                });
+            }
+        }
+        index += 1;
+    }
+    Ok(result)
+}
+
+/// Parses the lines of a `<gallery>` tag spanning `start..end` into gallery entries.
+///
+/// `parse_wiki_text_2` parses the content of the tag as regular wikitext, so each line is
+/// split from the source and its caption is simplified from the nodes within it.
+fn simplify_gallery_entries(
+    wikitext: &str,
+    nodes: &[pwt::Node],
+    start: usize,
+    end: usize,
+    config: &SimplificationConfig,
+    diagnostics: &mut DiagnosticCollector,
+) -> Result<Vec<WikitextSimplifiedGalleryEntry>, SimplificationError> {
+    let tag_content = &wikitext[start..end];
+    let (content_start, content_end) = match (tag_content.find('>'), tag_content.rfind("</")) {
+        (Some(open_end), Some(close_start)) if open_end < close_start => {
+            (start + open_end + 1, start + close_start)
+        }
+        // Self-closing or unclosed galleries have no entries
+        _ => return Ok(vec![]),
+    };
+
+    let mut entries = vec![];
+    let mut line_start = content_start;
+    for line in wikitext[content_start..content_end].split('\n') {
+        let line_end = line_start + line.len();
+        let trimmed_start = line_start + (line.len() - line.trim_start().len());
+        let trimmed_end = line_start + line.trim_end().len();
+        line_start = line_end + 1;
+        if trimmed_start >= trimmed_end {
+            continue;
+        }
+
+        let mut segments = split_top_level_pipes(wikitext, trimmed_start, trimmed_end).into_iter();
+        let Some(file_segment) = segments.next() else {
+            continue;
+        };
+        let file = wikitext[file_segment.start..file_segment.end].trim();
+        // As with MediaWiki, files are in the file namespace unless another one is given
+        let file = match file
+            .split_once(':')
+            .and_then(|(prefix, title)| Some((config.link_prefixes.namespace(prefix)?, title)))
+        {
+            Some((namespace, title)) => format!("{namespace}:{}", title.trim()),
+            None => format!("File:{file}"),
+        };
+
+        let mut options = ImageOptions::default();
+        let mut caption = None;
+        for segment in segments {
+            if !options.apply(&wikitext[segment.start..segment.end]) {
+                // As with inline images, the last unrecognised option is the caption
+                caption = Some(segment);
+            }
+        }
+
+        entries.push(WikitextSimplifiedGalleryEntry {
+            file,
+            options,
+            caption: caption
+                .map(|range| {
+                    simplify_wikitext_nodes_in_range(wikitext, nodes, range, config, diagnostics)
+                })
+                .transpose()?
+                .unwrap_or_default(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Adds the source text between `outer` and `inner` to the start and end of the given nodes,
+/// merging it into adjacent text nodes where possible.
+fn pad_with_source_text(
+    wikitext: &str,
+    nodes: &mut Vec<Spanned<WikitextSimplifiedNode>>,
+    outer: Span,
+    inner: Span,
+) {
+    if outer.start < inner.start {
+        let prefix = &wikitext[outer.start..inner.start];
+        match nodes.first_mut() {
+            Some(Spanned {
+                value: WikitextSimplifiedNode::Text { text },
+                span,
+            }) if span.start == inner.start => {
+                text.insert_str(0, prefix);
+                span.start = outer.start;
+            }
+            _ => nodes.insert(
+                0,
+                Spanned {
+                    value: WikitextSimplifiedNode::Text {
+                        text: prefix.to_string(),
+                    },
+                    span: Span {
+                        start: outer.start,
+                        end: inner.start,
+                    },
+                },
+            ),
+        }
+    }
+
+    if inner.end < outer.end {
+        let suffix = &wikitext[inner.end..outer.end];
+        match nodes.last_mut() {
+            Some(Spanned {
+                value: WikitextSimplifiedNode::Text { text },
+                span,
+            }) if span.end == inner.end => {
+                text.push_str(suffix);
+                span.end = outer.end;
+            }
+            _ => nodes.push(Spanned {
+                value: WikitextSimplifiedNode::Text {
+                    text: suffix.to_string(),
+                },
+                span: Span {
+                    start: inner.end,
+                    end: outer.end,
+                },
+            }),
+        }
+    }
+}
+
+/// Returns the span covered by the given nodes, or `None` if there are no nodes.
+fn nodes_span(nodes: &[pwt::Node]) -> Option<Span> {
+    Some(Span {
+        start: NodeMetadata::for_node(nodes.first()?).start,
+        end: NodeMetadata::for_node(nodes.last()?).end,
+    })
+}
+
+/// Splits `wikitext[start..end]` on pipes that are not nested within links or templates,
+/// returning the span of each segment.
+fn split_top_level_pipes(wikitext: &str, start: usize, end: usize) -> Vec<Span> {
+    let bytes = wikitext.as_bytes();
+    let mut segments = vec![];
+    let mut segment_start = start;
+    let mut link_depth = 0usize;
+    let mut template_depth = 0usize;
+    let mut index = start;
+    while index < end {
+        let pair = &bytes[index..(index + 2).min(end)];
+        match pair {
+            b"[[" => link_depth += 1,
+            b"]]" if link_depth > 0 => link_depth -= 1,
+            b"{{" => template_depth += 1,
+            b"}}" if template_depth > 0 => template_depth -= 1,
+            _ => {
+                if bytes[index] == b'|' && link_depth == 0 && template_depth == 0 {
+                    segments.push(Span {
+                        start: segment_start,
+                        end: index,
+                    });
+                    segment_start = index + 1;
+                }
+                index += 1;
+                continue;
+            }
+        }
+        index += 2;
+    }
+    segments.push(Span {
+        start: segment_start,
+        end,
+    });
+    segments
+}
+
+/// Tags whose content MediaWiki does not parse as wikitext, and which should thus not have
+/// bare URLs linked.
+const RAW_CONTENT_TAGS: [&str; 13] = [
+    "math",
+    "chem",
+    "ce",
+    "syntaxhighlight",
+    "source",
+    "score",
+    "timeline",
+    "templatedata",
+    "templatestyles",
+    "mapframe",
+    "maplink",
+    "graph",
+    "hiero",
+];
+
+/// Turns bare URLs in text into [`WikitextSimplifiedNode::ExtLink`]s, as MediaWiki does.
+///
+/// This is done after simplification, as URLs are not linked within the text of other links.
+fn autolink_bare_urls(
+    wikitext: &str,
+    nodes: &mut Vec<Spanned<WikitextSimplifiedNode>>,
+    prefixes: &LinkPrefixes,
+) {
+    use WikitextSimplifiedNode as WSN;
+    for mut node in std::mem::take(nodes) {
+        match node.value {
+            WSN::Text { text } => {
+                nodes.extend(autolink_bare_urls_in_text(text, node.span, prefixes))
+            }
+            _ => {
+                autolink_bare_urls_in_node(wikitext, &mut node, prefixes);
+                nodes.push(node);
+            }
+        }
+    }
+}
+
+fn autolink_bare_urls_in_node(
+    wikitext: &str,
+    node: &mut Spanned<WikitextSimplifiedNode>,
+    prefixes: &LinkPrefixes,
+) {
+    use WikitextSimplifiedNode as WSN;
+    // `<pre>` tags are preformatted like lines starting with a space, but their
+    // content is not parsed
+    let is_pre_tag = wikitext
+        .get(node.span.start..node.span.start + 4)
+        .is_some_and(|tag| tag.eq_ignore_ascii_case("<pre"));
+    match &mut node.value {
+        WSN::Link { .. } | WSN::ExtLink { .. } => {}
+        WSN::Preformatted { .. } if is_pre_tag => {}
+        WSN::Tag { name, .. } if RAW_CONTENT_TAGS.contains(&name.as_str()) => {}
+        WSN::Template { parameters, .. } => {
+            for parameter in parameters {
+                autolink_bare_urls(wikitext, &mut parameter.value, prefixes);
+            }
+        }
+        WSN::TemplateParameterUse { default, .. } => {
+            if let Some(default) = default {
+                autolink_bare_urls(wikitext, default, prefixes);
+            }
+        }
+        WSN::Image { caption, .. } => autolink_bare_urls(wikitext, caption, prefixes),
+        WSN::Gallery { entries, .. } => {
+            for entry in entries {
+                autolink_bare_urls(wikitext, &mut entry.caption, prefixes);
+            }
+        }
+        WSN::Table { captions, rows, .. } => {
+            for caption in captions {
+                autolink_bare_urls(wikitext, &mut caption.content, prefixes);
+            }
+            for cell in rows.iter_mut().flat_map(|row| row.cells.iter_mut()) {
+                autolink_bare_urls(wikitext, &mut cell.content, prefixes);
+            }
+        }
+        WSN::OrderedList { items } | WSN::UnorderedList { items } => {
+            for item in items {
+                autolink_bare_urls(wikitext, &mut item.content, prefixes);
+            }
+        }
+        WSN::DefinitionList { items } => {
+            for item in items {
+                autolink_bare_urls(wikitext, &mut item.content, prefixes);
+            }
+        }
+        value => {
+            if let Some(children) = value.children_mut() {
+                autolink_bare_urls(wikitext, children, prefixes);
+            }
+        }
+    }
+}
+
+/// Splits a text node into text and [`WikitextSimplifiedNode::ExtLink`]s for any bare URLs
+/// within it, following MediaWiki's rules for free external links.
+fn autolink_bare_urls_in_text(
+    text: String,
+    span: Span,
+    prefixes: &LinkPrefixes,
+) -> Vec<Spanned<WikitextSimplifiedNode>> {
+    use WikitextSimplifiedNode as WSN;
+
+    // Spans can only be recovered if the text matches the source
+    if text.len() != span.end - span.start {
+        return vec![Spanned {
+            value: WSN::Text { text },
+            span,
+        }];
+    }
+
+    let is_url_character = |c: char| {
+        !(matches!(c, '[' | ']' | '<' | '>' | '"' | '\u{7F}' | '\u{FFFD}')
+            || c <= ' '
+            || c.is_whitespace())
+    };
+    let text_node = |start: usize, end: usize| Spanned {
+        value: WSN::Text {
+            text: text[start..end].to_string(),
+        },
+        span: Span {
+            start: span.start + start,
+            end: span.start + end,
+        },
+    };
+
+    let mut output = vec![];
+    let mut text_start = 0;
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        // URLs must start at a word boundary, and protocol-relative URLs are not linked
+        let at_word_boundary = text[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_'));
+        let protocol = if c.is_ascii_alphabetic() && at_word_boundary {
+            prefixes
+                .protocol(&text[index..])
+                .filter(|protocol| *protocol != "//")
+        } else {
+            None
+        };
+
+        if let Some(protocol) = protocol {
+            let url = &text[index..];
+            let url = &url[..url.find(|c| !is_url_character(c)).unwrap_or(url.len())];
+            let url = trim_url_trailing_punctuation(url);
+            if url.len() > protocol.len() {
+                if text_start < index {
+                    output.push(text_node(text_start, index));
+                }
+                output.push(Spanned {
+                    value: WSN::ExtLink {
+                        link: url.to_string(),
+                        protocol: protocol.to_string(),
+                        text: None,
+                        bracketed: false,
+                    },
+                    span: Span {
+                        start: span.start + index,
+                        end: span.start + index + url.len(),
+                    },
+                });
+                index += url.len();
+                text_start = index;
+                continue;
+            }
+        }
+        index += c.len_utf8();
+    }
+
+    if output.is_empty() {
+        return vec![Spanned {
+            value: WSN::Text { text },
+            span,
+        }];
+    }
+    if text_start < text.len() {
+        output.push(text_node(text_start, text.len()));
+    }
+    output
+}
+
+/// Removes the trailing punctuation that MediaWiki does not consider part of a bare URL,
+/// including a closing parenthesis if the URL has no opening parenthesis.
+fn trim_url_trailing_punctuation(url: &str) -> &str {
+    let is_separator = |c: char| {
+        matches!(c, ',' | ';' | '.' | ':' | '!' | '?') || (c == ')' && !url.contains('('))
+    };
+    let trimmed = url.trim_end_matches(is_separator);
+
+    // Don't break a trailing character entity by removing its semicolon
+    let with_semicolon = &url[..(trimmed.len() + 1).min(url.len())];
+    let is_entity = with_semicolon
+        .strip_suffix(';')
+        .and_then(|url| url.rsplit_once('&'))
+        .is_some_and(|(_, entity)| {
+            let entity = entity.strip_prefix('#').unwrap_or(entity);
+            !entity.is_empty() && entity.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    if is_entity {
+        with_semicolon
+    } else {
+        trimmed
+    }
+}
+
 struct RootStack<'a> {
     stack: Vec<(WikitextSimplifiedNode, usize)>,
     wikitext: &'a str,
     current_node: Option<&'a pwt::Node<'a>>,
+    /// Formatting that was closed by the closing tag of a layer it was opened within, from
+    /// the outermost to the innermost, with where it was first opened
+    reopen: Vec<(WikitextSimplifiedNode, usize)>,
 }
 impl<'a> RootStack<'a> {
     fn new(wikitext: &'a str) -> Self {
@@ -1365,10 +2931,12 @@ impl<'a> RootStack<'a> {
             stack: vec![(WikitextSimplifiedNode::Fragment { children: vec![] }, 0)],
             wikitext,
             current_node: None,
+            reopen: vec![],
         }
     }
 
     fn push_layer(&mut self, node: WikitextSimplifiedNode, start: usize) {
+        self.reopen_formatting(start);
         self.stack.push((node, start));
     }
 
@@ -1389,25 +2957,247 @@ impl<'a> RootStack<'a> {
         })
     }
 
-    fn last_layer(&self) -> &WikitextSimplifiedNode {
-        &self.stack.last().unwrap().0
+    /// Closes the innermost open layer for the tag with the given name.
+    ///
+    /// As with the adoption agency algorithm that browsers use for MediaWiki's output,
+    /// layers opened within it are closed at the start of the closing tag, and those that
+    /// are formatting are reopened before the next content, e.g. `<small>a<b>b</small>c</b>`
+    /// becomes `<small>a<b>b</b></small><b>c</b>`. Unlike in browsers, block elements within
+    /// formatting are closed with it rather than moved out of it.
+    ///
+    /// A closing tag for formatting that is waiting to be reopened stops it from being
+    /// reopened, and any other closing tag without a matching open layer is discarded, as
+    /// MediaWiki does.
+    fn close_tag(
+        &mut self,
+        name: &str,
+        markup: Span,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<(), SimplificationError> {
+        if self.close_matching_layer(
+            |node| layer_tag_name(node) == Some(name),
+            markup,
+            diagnostics,
+        )? {
+            return Ok(());
+        }
+        if diagnostics.recover {
+            diagnostics.push(Diagnostic {
+                severity: DiagnosticSeverity::Warning,
+                kind: DiagnosticKind::UnmatchedClosingTag,
+                span: markup,
+                message: format!("`</{name}>` does not close any open tag, and was discarded"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Closes the innermost open layer for the formatting at the end of `markup`, as
+    /// [`RootStack::close_tag`] does for tags. Formatting that is not open, such as when it
+    /// was closed by a tag it was opened within, is ignored.
+    fn close_formatting(
+        &mut self,
+        quote: Quote,
+        markup: Span,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<(), SimplificationError> {
+        self.close_matching_layer(|node| quote.matches(node), markup, diagnostics)?;
+        Ok(())
     }
 
-    /// Checks if there's a matching Tag with the given name on the stack.
-    /// Returns the tag name if found, None otherwise.
-    fn find_matching_tag(&self, name: &str) -> Option<&str> {
-        // Check if the last layer is a Tag with the matching name
-        if let WikitextSimplifiedNode::Tag { name: tag_name, .. } = self.last_layer() {
-            if tag_name == name {
-                return Some(tag_name);
+    /// Closes the innermost open layer that matches, as described by
+    /// [`RootStack::close_tag`], returning whether there was a matching layer that was
+    /// open or waiting to be reopened.
+    fn close_matching_layer(
+        &mut self,
+        is_match: impl Fn(&WikitextSimplifiedNode) -> bool,
+        markup: Span,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<bool, SimplificationError> {
+        let Some(index) = self.stack.iter().rposition(|(node, _)| is_match(node)) else {
+            let Some(index) = self.reopen.iter().rposition(|(node, _)| is_match(node)) else {
+                return Ok(false);
+            };
+            self.reopen.remove(index);
+            return Ok(true);
+        };
+
+        let mut reopen = vec![];
+        while self.stack.len() > index + 1 {
+            if !self
+                .stack
+                .last()
+                .is_some_and(|(node, _)| is_formatting_layer(node))
+            {
+                self.close_layer_implicitly(markup.start, diagnostics)?;
+                continue;
             }
+            let layer = self.pop_layer(markup.start)?;
+            if diagnostics.recover {
+                diagnostics.push(Diagnostic {
+                    severity: DiagnosticSeverity::Warning,
+                    kind: DiagnosticKind::MisnestedFormatting,
+                    span: layer.span,
+                    message: format!(
+                        "{} was still open when an element it was opened within was closed, \
+                         and was reopened after it",
+                        layer_description(&layer.value)
+                    ),
+                });
+            }
+            let mut node = layer.value.clone();
+            if let Some(children) = node.children_mut() {
+                children.clear();
+            }
+            reopen.push((node, layer.span.start));
+            self.add_to_children(layer)?;
         }
-        None
+        let layer = self.pop_layer(markup.end)?;
+        self.add_to_children(layer)?;
+
+        // Formatting waiting to be reopened from before was opened within these layers
+        reopen.reverse();
+        reopen.append(&mut self.reopen);
+        self.reopen = reopen;
+        Ok(true)
     }
 
-    /// Pushes a previously popped layer back onto the stack
-    fn push_layer_back(&mut self, spanned: Spanned<WikitextSimplifiedNode>) {
-        self.stack.push((spanned.value, spanned.span.start));
+    /// Reopens the formatting waiting to be reopened at `start`, before content is added
+    /// there.
+    fn reopen_formatting(&mut self, start: usize) {
+        let reopen = std::mem::take(&mut self.reopen);
+        self.stack
+            .extend(reopen.into_iter().map(|(node, _)| (node, start)));
+    }
+
+    /// Closes the innermost open layers while they are formatting whose line ended at or
+    /// before `position`.
+    ///
+    /// Formatting within a tag that is still open is left open until the tag is closed.
+    fn close_ended_formatting(
+        &mut self,
+        position: usize,
+        quotes: &QuoteResolution,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<(), SimplificationError> {
+        self.reopen
+            .retain(|(node, start)| !is_quote_layer(node) || quotes.line_end(*start) > position);
+        while let [_, .., (node, start)] = self.stack.as_slice() {
+            let line_end = quotes.line_end(*start);
+            if !is_quote_layer(node) || line_end > position {
+                break;
+            }
+            // The formatting may contain a tag that continued past the end of the line
+            let children_end = node
+                .children()
+                .and_then(|children| children.last())
+                .map_or(line_end, |child| child.span.end);
+            self.close_layer_implicitly(line_end.max(children_end), diagnostics)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the node to the children of the innermost layer, splitting text at the end of
+    /// the line of the formatting it is within so that the formatting ends there.
+    fn add_to_children_within_line(
+        &mut self,
+        node: Spanned<WikitextSimplifiedNode>,
+        quotes: &QuoteResolution,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<(), SimplificationError> {
+        self.reopen_formatting(node.span.start);
+        let line_end = match (&node.value, self.stack.as_slice()) {
+            (WikitextSimplifiedNode::Text { text }, [_, .., (layer, start)])
+                if is_quote_layer(layer) && text.len() == node.span.end - node.span.start =>
+            {
+                Some(quotes.line_end(*start))
+                    .filter(|&line_end| line_end > node.span.start && line_end < node.span.end)
+            }
+            _ => None,
+        };
+        let Some(line_end) = line_end else {
+            return self.add_to_children(node);
+        };
+
+        let WikitextSimplifiedNode::Text { text } = node.value else {
+            return Ok(());
+        };
+        let (before, after) = text.split_at(line_end - node.span.start);
+        self.add_to_children(Spanned {
+            value: WikitextSimplifiedNode::Text {
+                text: before.to_string(),
+            },
+            span: Span {
+                start: node.span.start,
+                end: line_end,
+            },
+        })?;
+        self.close_ended_formatting(line_end, quotes, diagnostics)?;
+        self.add_to_children(Spanned {
+            value: WikitextSimplifiedNode::Text {
+                text: after.to_string(),
+            },
+            span: Span {
+                start: line_end,
+                end: node.span.end,
+            },
+        })
+    }
+
+    /// Adds text from the wikitext to the children of the innermost layer, appending it to
+    /// the preceding text if they are adjacent.
+    fn add_text(&mut self, span: Span) -> Result<(), SimplificationError> {
+        self.reopen_formatting(span.start);
+        let text = &self.wikitext[span.start..span.end];
+        if let Some(Spanned {
+            value: WikitextSimplifiedNode::Text { text: previous },
+            span: previous_span,
+        }) = self
+            .stack
+            .last_mut()
+            .and_then(|(layer, _)| layer.children_mut())
+            .and_then(|children| children.last_mut())
+        {
+            if previous_span.end == span.start {
+                previous.push_str(text);
+                previous_span.end = span.end;
+                return Ok(());
+            }
+        }
+        self.add_to_children(Spanned {
+            value: WikitextSimplifiedNode::Text {
+                text: text.to_string(),
+            },
+            span,
+        })
+    }
+
+    /// Closes the innermost open layer at `end`, reporting that it was not closed
+    /// explicitly when recovering from malformed wikitext.
+    fn close_layer_implicitly(
+        &mut self,
+        end: usize,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<(), SimplificationError> {
+        let layer = self.pop_layer(end)?;
+        if !diagnostics.recover {
+            return self.add_to_children(layer);
+        }
+        let kind = if is_quote_layer(&layer.value) {
+            DiagnosticKind::UnclosedFormatting
+        } else {
+            DiagnosticKind::UnclosedTag
+        };
+        diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Warning,
+            kind,
+            span: layer.span,
+            message: format!(
+                "{} was not closed, and was closed implicitly",
+                layer_description(&layer.value)
+            ),
+        });
+        self.add_to_children(layer)
     }
 
     fn add_to_children(
@@ -1435,13 +3225,16 @@ impl<'a> RootStack<'a> {
         Ok(())
     }
 
-    fn unwind(mut self) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
-        // This is a disgusting hack, but Wikipedia implicitly closes these, so we need to as well...
+    fn unwind(
+        mut self,
+        diagnostics: &mut DiagnosticCollector,
+    ) -> Result<Vec<Spanned<WikitextSimplifiedNode>>, SimplificationError> {
+        // Tags left open are implicitly closed, as MediaWiki does. Formatting has already been
+        // closed at the end of its line, unless it is within one of these tags.
         // Use the end of wikitext as the end position for implicitly closed tags
         let wikitext_end = self.wikitext.len();
         while self.stack.len() > 1 {
-            let popped = self.pop_layer(wikitext_end)?;
-            self.add_to_children(popped)?;
+            self.close_layer_implicitly(wikitext_end, diagnostics)?;
         }
         Ok(self.stack[0].0.children().unwrap().to_vec())
     }
@@ -1469,20 +3262,114 @@ impl<'a> RootStack<'a> {
     }
 }
 
-/// Helper function to extract attributes from an HTML tag's opening content
-fn extract_tag_attributes(opening_tag: &str) -> Option<String> {
-    opening_tag.find(char::is_whitespace).map(|attr_start| {
-        let attr_str = opening_tag[attr_start..].trim();
-        if let Some(stripped) = attr_str.strip_suffix('>') {
-            let trimmed = stripped.trim();
-            // Ensure the attribute string ends with a quote if it starts with one
-            if trimmed.starts_with('"') && !trimmed.ends_with('"') {
-                format!("{trimmed}\"")
-            } else {
-                trimmed.to_string()
-            }
-        } else {
-            attr_str.to_string()
+/// Returns the name of the tag that opens the given layer of a [`RootStack`], if it was
+/// opened by a tag.
+fn layer_tag_name(node: &WikitextSimplifiedNode) -> Option<&str> {
+    use WikitextSimplifiedNode as WSN;
+    match node {
+        WSN::Blockquote { .. } => Some("blockquote"),
+        WSN::Superscript { .. } => Some("sup"),
+        WSN::Subscript { .. } => Some("sub"),
+        WSN::Small { .. } => Some("small"),
+        WSN::Preformatted { .. } => Some("pre"),
+        WSN::Tag { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// Returns whether the given layer of a [`RootStack`] is bold or italic formatting.
+fn is_quote_layer(node: &WikitextSimplifiedNode) -> bool {
+    matches!(
+        node,
+        WikitextSimplifiedNode::Bold { .. } | WikitextSimplifiedNode::Italic { .. }
+    )
+}
+
+/// Returns whether browsers reopen the given layer of a [`RootStack`] after it is closed by
+/// the closing tag of an element it was opened within, i.e. whether it is one of HTML's
+/// formatting elements.
+fn is_formatting_layer(node: &WikitextSimplifiedNode) -> bool {
+    const FORMATTING_TAGS: &[&str] = &[
+        "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt",
+        "u",
+    ];
+    is_quote_layer(node)
+        || layer_tag_name(node).is_some_and(|name| {
+            FORMATTING_TAGS
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Describes the given layer of a [`RootStack`] for diagnostics, e.g. `Bold` or `` `<b>` ``.
+fn layer_description(node: &WikitextSimplifiedNode) -> String {
+    match node {
+        WikitextSimplifiedNode::Bold { .. } => "Bold".to_string(),
+        WikitextSimplifiedNode::Italic { .. } => "Italic".to_string(),
+        node => format!("`<{}>`", layer_tag_name(node).unwrap_or(node.node_type())),
+    }
+}
+
+/// Returns the list markers at the start of the line of the list item starting at
+/// `item_start` with the given content, e.g. `*#` for `*# item`.
+fn list_item_marker(
+    wikitext: &str,
+    item_start: usize,
+    content: &[Spanned<WikitextSimplifiedNode>],
+) -> String {
+    let line_start = wikitext[..item_start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line = &wikitext[line_start..];
+    let marker_end = line
+        .find(|c: char| !matches!(c, '*' | '#' | ':' | ';'))
+        .unwrap_or(line.len());
+    let mut marker = line[..marker_end].to_string();
+
+    if item_start > line_start + marker_end {
+        // Details following a term on the same line (`;term:details`) take its place
+        marker.pop();
+        marker.push(':');
+    } else if let Some(nested_marker) = content
+        .first()
+        .and_then(|node| node.value.first_list_item_marker())
+    {
+        // An item that only holds a nested list shares the line of the nested list's
+        // first item (e.g. `** item` without a preceding `* item`)
+        if nested_marker == marker {
+            marker.pop();
         }
-    })
+    }
+    marker
+}
+
+/// Converts the attributes of a `<ref>` or `<references>` tag back to wikitext, including
+/// the leading space if there are any.
+fn reference_attributes_to_wikitext(name: Option<&str>, group: Option<&str>) -> String {
+    let mut output = String::new();
+    for (key, value) in [("name", name), ("group", group)] {
+        if let Some(value) = value {
+            output.push_str(&format!(" {key}=\"{value}\""));
+        }
+    }
+    output
+}
+
+/// Extracts the attributes from the opening tag of a tag spanning `start..end`, e.g.
+/// `<div class="foo">`, along with the attributes as written.
+fn extract_tag_attributes(
+    wikitext: &str,
+    start: usize,
+    end: usize,
+) -> (Vec<TagAttribute>, Option<String>) {
+    let tag_content = &wikitext[start..end];
+    let closing_bracket_pos = tag_content.find('>').unwrap_or(tag_content.len());
+    let opening_tag = &tag_content[..closing_bracket_pos];
+    let Some(attr_start) = opening_tag.find(char::is_whitespace) else {
+        return (vec![], None);
+    };
+    (
+        parse_tag_attributes(&opening_tag[attr_start..], start + attr_start),
+        Some(opening_tag[attr_start..].trim().to_string()),
+    )
 }
@@ -0,0 +1,222 @@
+//! Layout of [`crate::WikitextSimplifiedNode::Table`] nodes into a grid, resolving the
+//! `rowspan` and `colspan` of their cells.
+
+use crate::{
+    attributes::{find_tag_attribute, parse_tag_attributes},
+    TagAttribute, WikitextSimplifiedNode, WikitextSimplifiedTableCell, WikitextSimplifiedTableRow,
+};
+
+/// The largest `colspan` browsers will honour
+const MAX_COLSPAN: usize = 1000;
+/// The largest `rowspan` browsers will honour
+const MAX_ROWSPAN: usize = 65534;
+
+impl WikitextSimplifiedTableCell {
+    /// Parses the HTML attributes of the cell.
+    ///
+    /// The spans of the attributes are only accurate if the attributes are plain text, as
+    /// any templates within them are written back out as wikitext before parsing.
+    pub fn parsed_attributes(&self) -> Vec<TagAttribute> {
+        let Some(attributes) = &self.attributes else {
+            return vec![];
+        };
+        let text: String = attributes
+            .iter()
+            .map(|node| node.value.to_wikitext())
+            .collect();
+        let start = attributes.first().map_or(0, |node| node.span.start);
+        parse_tag_attributes(&text, start)
+    }
+
+    /// Returns the number of rows this cell spans, following the HTML rules for invalid
+    /// values.
+    ///
+    /// A `rowspan` of `0` spans the rest of the table, and is returned as `0`.
+    pub fn rowspan(&self) -> usize {
+        self.span_attribute("rowspan", 0)
+            .map_or(1, |rowspan| rowspan.min(MAX_ROWSPAN))
+    }
+
+    /// Returns the number of columns this cell spans, following the HTML rules for invalid
+    /// values.
+    pub fn colspan(&self) -> usize {
+        self.span_attribute("colspan", 1)
+            .map_or(1, |colspan| colspan.min(MAX_COLSPAN))
+    }
+
+    /// Parses a span attribute as a non-negative integer of at least `min`, ignoring any
+    /// trailing non-digits (e.g. `2px`) as browsers do.
+    fn span_attribute(&self, name: &str, min: usize) -> Option<usize> {
+        let attributes = self.parsed_attributes();
+        let value = find_tag_attribute(&attributes, name)?.value.as_deref()?;
+        let value = value.trim_start();
+        let value = value.strip_prefix('+').unwrap_or(value);
+        let digits = value
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(value, |end| &value[..end]);
+        // Values too large to parse are clamped anyway
+        let span = match digits.parse::<usize>() {
+            Ok(span) => span,
+            Err(_) if !digits.is_empty() => usize::MAX,
+            Err(_) => return None,
+        };
+        (span >= min).then_some(span)
+    }
+}
+
+/// A cell of a [`TableGrid`], which covers one or more slots of the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableGridCell<'a> {
+    /// The cell in the table
+    pub cell: &'a WikitextSimplifiedTableCell,
+    /// The row of the top-left slot covered by the cell
+    pub row: usize,
+    /// The column of the top-left slot covered by the cell
+    pub column: usize,
+    /// The number of rows covered by the cell, after clipping it to the table
+    pub rowspan: usize,
+    /// The number of columns covered by the cell
+    pub colspan: usize,
+}
+impl TableGridCell<'_> {
+    /// Returns whether the given slot is the top-left slot covered by this cell, rather
+    /// than one it covers because of its `rowspan` or `colspan`.
+    pub fn is_origin(&self, row: usize, column: usize) -> bool {
+        self.row == row && self.column == column
+    }
+
+    /// Returns whether this is a header cell.
+    pub fn is_header(&self) -> bool {
+        self.cell.is_header
+    }
+}
+
+/// The layout of a table as a rectangular grid of slots, with cells that span several rows
+/// or columns covering each of the slots they span, as browsers lay them out.
+///
+/// Slots that are not covered by any cell, such as at the end of a row with fewer cells
+/// than the others, are empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableGrid<'a> {
+    slots: Vec<Vec<Option<TableGridCell<'a>>>>,
+    width: usize,
+}
+impl<'a> TableGrid<'a> {
+    /// Lays out the given rows of a table.
+    pub fn new(rows: &'a [WikitextSimplifiedTableRow]) -> Self {
+        let height = rows.len();
+        let mut slots: Vec<Vec<Option<TableGridCell<'a>>>> = vec![vec![]; height];
+        let mut width = 0;
+
+        for (row, table_row) in rows.iter().enumerate() {
+            let mut column = 0;
+            for cell in &table_row.cells {
+                // Skip slots covered by cells from previous rows
+                while slots[row].get(column).is_some_and(Option::is_some) {
+                    column += 1;
+                }
+
+                let rowspan = match cell.rowspan() {
+                    0 => height - row,
+                    rowspan => rowspan.min(height - row),
+                };
+                let colspan = cell.colspan();
+                let grid_cell = TableGridCell {
+                    cell,
+                    row,
+                    column,
+                    rowspan,
+                    colspan,
+                };
+                for covered_row in &mut slots[row..row + rowspan] {
+                    if covered_row.len() < column + colspan {
+                        covered_row.resize(column + colspan, None);
+                    }
+                    for slot in &mut covered_row[column..column + colspan] {
+                        // Overlapping cells keep the slot of the earlier cell
+                        slot.get_or_insert(grid_cell);
+                    }
+                }
+                column += colspan;
+                width = width.max(column);
+            }
+        }
+
+        for row in &mut slots {
+            row.resize(width, None);
+        }
+        Self { slots, width }
+    }
+
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the cell covering the given slot, if any.
+    pub fn get(&self, row: usize, column: usize) -> Option<&TableGridCell<'a>> {
+        self.slots.get(row)?.get(column)?.as_ref()
+    }
+
+    /// Returns the slots of each row of the grid.
+    pub fn rows(&self) -> impl Iterator<Item = &[Option<TableGridCell<'a>>]> {
+        self.slots.iter().map(Vec::as_slice)
+    }
+
+    /// Returns the slots of the given row.
+    pub fn row(&self, row: usize) -> Option<&[Option<TableGridCell<'a>>]> {
+        self.slots.get(row).map(Vec::as_slice)
+    }
+
+    /// Returns whether every cell in the given row is a header cell.
+    pub fn is_header_row(&self, row: usize) -> bool {
+        let Some(slots) = self.slots.get(row) else {
+            return false;
+        };
+        let mut cells = slots.iter().flatten().peekable();
+        cells.peek().is_some() && cells.all(TableGridCell::is_header)
+    }
+
+    /// Returns whether every cell in the given column is a header cell, such as the
+    /// first column of a table whose rows each start with a `!` cell.
+    ///
+    /// Header rows are not considered, so that a header row does not make every column a
+    /// header column.
+    pub fn is_header_column(&self, column: usize) -> bool {
+        let mut cells = (0..self.height())
+            .filter(|&row| !self.is_header_row(row))
+            .filter_map(|row| self.get(row, column))
+            .peekable();
+        cells.peek().is_some() && cells.all(TableGridCell::is_header)
+    }
+
+    /// Returns the indices of the rows in which every cell is a header cell.
+    pub fn header_rows(&self) -> Vec<usize> {
+        (0..self.height())
+            .filter(|&row| self.is_header_row(row))
+            .collect()
+    }
+
+    /// Returns the indices of the header columns, as defined by
+    /// [`TableGrid::is_header_column`].
+    pub fn header_columns(&self) -> Vec<usize> {
+        (0..self.width())
+            .filter(|&column| self.is_header_column(column))
+            .collect()
+    }
+}
+
+impl WikitextSimplifiedNode {
+    /// Lays out the rows of this node into a [`TableGrid`] if it is a table.
+    pub fn table_grid(&self) -> Option<TableGrid<'_>> {
+        match self {
+            Self::Table { rows, .. } => Some(TableGrid::new(rows)),
+            _ => None,
+        }
+    }
+}
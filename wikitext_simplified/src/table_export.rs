@@ -0,0 +1,304 @@
+//! Conversion of [`TableGrid`]s into data, as CSV or as records keyed by header.
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{collect_footnotes, Span, Spanned, TableGrid, WikitextSimplifiedNode};
+
+/// Templates that add a footnote marker to the text they are used in
+const FOOTNOTE_TEMPLATES: &[&str] = &[
+    "efn",
+    "efn-la",
+    "efn-lg",
+    "efn-lr",
+    "efn-ua",
+    "efn-ur",
+    "refn",
+    "sfn",
+    "sfnm",
+    "sfnp",
+    "r",
+    "rp",
+    "citation needed",
+    "cn",
+    "fact",
+];
+
+/// How the content of each cell is represented when exporting a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableCellContent {
+    /// The content is flattened to plain text with
+    /// [`WikitextSimplifiedNode::to_plain_text`], and trimmed
+    #[default]
+    PlainText,
+    /// The content is kept as simplified nodes.
+    ///
+    /// This is only used for records; CSV is always written as plain text.
+    Nodes,
+}
+
+/// Options for exporting a [`TableGrid`] with [`TableGrid::to_csv`] or
+/// [`TableGrid::to_records`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableExportOptions {
+    /// Whether a cell that spans several rows or columns has its value repeated in each
+    /// slot it covers. Otherwise, only the top-left slot has the value, and the others are
+    /// empty.
+    pub repeat_spanned_values: bool,
+    /// Whether to remove footnote markers, i.e. `<ref>`s and templates such as `{{efn}}`
+    /// and `{{citation needed}}`, from the content of cells.
+    ///
+    /// If they are kept, `<ref>`s are written in plain text as their numbered markers
+    /// (e.g. `[1]`), numbered within the table.
+    pub strip_footnotes: bool,
+    /// The row of the grid whose cells name the columns.
+    ///
+    /// If not specified, the last of the header rows at the top of the table is used.
+    /// Columns without a header are named by their number, starting from 1.
+    pub header_row: Option<usize>,
+    /// How the content of each cell is represented
+    pub cell_content: TableCellContent,
+}
+impl Default for TableExportOptions {
+    fn default() -> Self {
+        Self {
+            repeat_spanned_values: true,
+            strip_footnotes: true,
+            header_row: None,
+            cell_content: TableCellContent::default(),
+        }
+    }
+}
+
+/// The value of a slot of a table in a [`TableRecord`]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum TableValue {
+    /// The slot is empty. Serialized as `null`.
+    Empty,
+    /// The content of the cell as plain text. Serialized as a string.
+    Text(String),
+    /// The content of the cell as simplified nodes. Serialized as a list of nodes.
+    Nodes(Vec<Spanned<WikitextSimplifiedNode>>),
+}
+
+/// A row of data of a table, as exported by [`TableGrid::to_records`]
+///
+/// Serialized as a map from the names of the columns to their values, in the order of
+/// the columns.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TableRecord {
+    /// The name of each column and the value of the row in it, in the order of the columns
+    pub fields: Vec<(String, TableValue)>,
+}
+impl TableRecord {
+    /// Returns the value of the row in the column with the given name.
+    pub fn get(&self, column: &str) -> Option<&TableValue> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the names of the columns, in order.
+    pub fn columns(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|(name, _)| name.as_str())
+    }
+}
+impl Serialize for TableRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl TableGrid<'_> {
+    /// Converts the table into CSV, with a line for the names of the columns followed by
+    /// a line for each row of data, as in [`TableGrid::to_records`].
+    pub fn to_csv(&self, options: &TableExportOptions) -> String {
+        let options = TableExportOptions {
+            cell_content: TableCellContent::PlainText,
+            ..options.clone()
+        };
+        let (keys, rows) = self.export(&options);
+
+        let mut output = String::new();
+        for line in std::iter::once(keys.into_iter().map(TableValue::Text).collect()).chain(rows) {
+            let fields: Vec<String> = line
+                .iter()
+                .map(|value| match value {
+                    TableValue::Text(text) => csv_field(text),
+                    _ => String::new(),
+                })
+                .collect();
+            output.push_str(&fields.join(","));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Converts each row of data in the table into a [`TableRecord`], keyed by the names
+    /// of the columns.
+    ///
+    /// Only the rows after the header row that are not themselves header rows are
+    /// included as data. Columns with the same name have a number appended to the names
+    /// of the later columns (e.g. `Year_2`).
+    pub fn to_records(&self, options: &TableExportOptions) -> Vec<TableRecord> {
+        let (keys, rows) = self.export(options);
+        rows.into_iter()
+            .map(|row| TableRecord {
+                fields: keys.iter().cloned().zip(row).collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the names of the columns and the values of each row of data.
+    fn export(&self, options: &TableExportOptions) -> (Vec<String>, Vec<Vec<TableValue>>) {
+        let markers = if options.strip_footnotes {
+            vec![]
+        } else {
+            self.footnote_markers()
+        };
+        // Header cells are always repeated, so that each column they span is named
+        let content = |row: usize, column: usize, repeat: bool| {
+            let cell = self.get(row, column)?;
+            if !repeat && !cell.is_origin(row, column) {
+                return None;
+            }
+            let mut content = cell.cell.content.clone();
+            if options.strip_footnotes {
+                strip_footnotes(&mut content);
+            } else {
+                replace_footnotes_with_markers(&mut content, &markers);
+            }
+            Some(content)
+        };
+        let plain_text = |content: &[Spanned<WikitextSimplifiedNode>]| -> String {
+            let text: String = content
+                .iter()
+                .map(|node| node.value.to_plain_text())
+                .collect();
+            text.trim().to_string()
+        };
+
+        let header_row = options.header_row.or_else(|| {
+            (0..self.height())
+                .take_while(|&row| self.is_header_row(row))
+                .last()
+        });
+        let mut keys: Vec<String> = vec![];
+        for column in 0..self.width() {
+            let name = header_row
+                .and_then(|row| content(row, column, true))
+                .map(|content| plain_text(&content))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| (column + 1).to_string());
+            let mut key = name.clone();
+            let mut number = 2;
+            while keys.contains(&key) {
+                key = format!("{name}_{number}");
+                number += 1;
+            }
+            keys.push(key);
+        }
+
+        let value = |row: usize, column: usize| {
+            let Some(content) = content(row, column, options.repeat_spanned_values) else {
+                return TableValue::Empty;
+            };
+            match options.cell_content {
+                TableCellContent::PlainText => TableValue::Text(plain_text(&content)),
+                TableCellContent::Nodes => TableValue::Nodes(content),
+            }
+        };
+        let rows = (0..self.height())
+            .filter(|&row| header_row.is_none_or(|header_row| row > header_row))
+            .filter(|&row| !self.is_header_row(row))
+            .map(|row| (0..self.width()).map(|column| value(row, column)).collect())
+            .collect();
+
+        (keys, rows)
+    }
+
+    /// Numbers the `<ref>`s within the table, returning the marker for each use.
+    fn footnote_markers(&self) -> Vec<(Span, String)> {
+        let nodes: Vec<_> = self
+            .rows()
+            .enumerate()
+            .flat_map(|(row, slots)| {
+                slots.iter().enumerate().filter_map(move |(column, slot)| {
+                    slot.filter(|cell| cell.is_origin(row, column))
+                })
+            })
+            .flat_map(|cell| cell.cell.content.iter().cloned())
+            .collect();
+
+        collect_footnotes(&nodes)
+            .into_iter()
+            .flat_map(|list| {
+                list.footnotes.into_iter().flat_map(move |footnote| {
+                    let marker = match &list.group {
+                        Some(group) => format!("[{group} {}]", footnote.number),
+                        None => format!("[{}]", footnote.number),
+                    };
+                    footnote
+                        .uses
+                        .into_iter()
+                        .map(move |span| (span, marker.clone()))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns whether the node is a footnote marker.
+fn is_footnote(node: &WikitextSimplifiedNode) -> bool {
+    match node {
+        WikitextSimplifiedNode::Reference { .. } => true,
+        WikitextSimplifiedNode::Template {
+            normalized_name, ..
+        } => FOOTNOTE_TEMPLATES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&normalized_name.title)),
+        _ => false,
+    }
+}
+
+/// Removes footnote markers from the nodes and their children.
+fn strip_footnotes(nodes: &mut Vec<Spanned<WikitextSimplifiedNode>>) {
+    nodes.retain(|node| !is_footnote(&node.value));
+    for node in nodes {
+        if let Some(children) = node.value.children_mut() {
+            strip_footnotes(children);
+        }
+    }
+}
+
+/// Replaces `<ref>`s in the nodes and their children with text of their markers.
+fn replace_footnotes_with_markers(
+    nodes: &mut [Spanned<WikitextSimplifiedNode>],
+    markers: &[(Span, String)],
+) {
+    for node in nodes {
+        if let WikitextSimplifiedNode::Reference { .. } = node.value {
+            if let Some((_, marker)) = markers.iter().find(|(span, _)| *span == node.span) {
+                node.value = WikitextSimplifiedNode::Text {
+                    text: marker.clone(),
+                };
+            }
+        } else if let Some(children) = node.value.children_mut() {
+            replace_footnotes_with_markers(children, markers);
+        }
+    }
+}
+
+/// Quotes a field for CSV if it contains a delimiter, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
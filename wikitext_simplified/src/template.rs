@@ -0,0 +1,194 @@
+//! Typed access to the names and parameters of [`WikitextSimplifiedNode::Template`] nodes.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+use tsify_next::Tsify;
+
+use wikitext_util::{
+    normalize_page_title, normalize_parameter_name, wikipedia_link_prefixes, LinkPrefixes,
+};
+
+use crate::{Span, Spanned, TemplateParameter, WikitextSimplifiedNode};
+
+/// A prefix that changes how a template is transcluded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum TemplateModifier {
+    /// `subst:`, which replaces the template with its expansion when the page is saved
+    Subst,
+    /// `safesubst:`, which behaves like `subst:` but is ignored when not substituting
+    SafeSubst,
+    /// `msgnl:`, which transcludes the template without treating its start as a new line
+    Msgnl,
+}
+
+/// The page transcluded by a template, resolved from the name as written
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TemplateName {
+    /// The normalized title of the page, without its namespace (e.g. `Infobox person` for
+    /// `{{ Template:infobox_person }}`)
+    pub title: String,
+    /// The canonical name of the namespace of the page.
+    ///
+    /// This is `Template` unless another namespace was given, and empty for pages in the
+    /// main namespace (e.g. `{{:Main Page}}`).
+    pub namespace: String,
+    /// The modifiers that preceded the name, in order of appearance
+    pub modifiers: Vec<TemplateModifier>,
+}
+impl TemplateName {
+    /// Resolves the name of a template as MediaWiki does, stripping any modifiers and
+    /// normalizing the title.
+    ///
+    /// Helper function that calls [`TemplateName::parse_with_prefixes`] with the prefixes used
+    /// by Wikipedia.
+    pub fn parse(name: &str) -> Self {
+        Self::parse_with_prefixes(name, wikipedia_link_prefixes())
+    }
+
+    /// Resolves the name of a template as MediaWiki does, stripping any modifiers and
+    /// normalizing the title, using the given prefixes to recognise namespaces.
+    pub fn parse_with_prefixes(name: &str, prefixes: &LinkPrefixes) -> Self {
+        let mut name = name.trim();
+
+        let mut modifiers = vec![];
+        while let Some((prefix, rest)) = name.split_once(':') {
+            let modifier = match prefix.trim().to_lowercase().as_str() {
+                "subst" => TemplateModifier::Subst,
+                "safesubst" => TemplateModifier::SafeSubst,
+                "msgnl" => TemplateModifier::Msgnl,
+                _ => break,
+            };
+            modifiers.push(modifier);
+            name = rest.trim_start();
+        }
+
+        let (namespace, title) = if let Some(title) = name.strip_prefix(':') {
+            ("", title)
+        } else {
+            name.split_once(':')
+                .and_then(|(prefix, title)| Some((prefixes.namespace(prefix)?, title)))
+                .unwrap_or(("Template", name))
+        };
+
+        Self {
+            title: normalize_page_title(title),
+            namespace: namespace.to_string(),
+            modifiers,
+        }
+    }
+
+    /// Returns the full title of the page, including its namespace (e.g.
+    /// `Template:Infobox person`).
+    pub fn full_title(&self) -> String {
+        if self.namespace.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{}:{}", self.namespace, self.title)
+        }
+    }
+}
+
+/// A view over a [`WikitextSimplifiedNode::Template`] for looking up its parameters by name
+/// or position.
+///
+/// Obtained through [`WikitextSimplifiedNode::as_template`].
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateRef<'a> {
+    name: &'a str,
+    normalized_name: &'a TemplateName,
+    name_span: Span,
+    parameters: &'a [TemplateParameter],
+}
+impl<'a> TemplateRef<'a> {
+    /// Returns the name of the template, as written.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the page transcluded by the template.
+    pub fn normalized_name(&self) -> &'a TemplateName {
+        self.normalized_name
+    }
+
+    /// Returns the span of the name of the template in the source.
+    pub fn name_span(&self) -> Span {
+        self.name_span
+    }
+
+    /// Returns all parameters of the template, in order of appearance.
+    pub fn parameters(&self) -> &'a [TemplateParameter] {
+        self.parameters
+    }
+
+    /// Returns the parameter with the given name, ignoring case and whitespace.
+    pub fn get(&self, name: &str) -> Option<&'a TemplateParameter> {
+        let name = normalize_parameter_name(name);
+        self.parameters
+            .iter()
+            .rev()
+            .find(|parameter| normalize_parameter_name(&parameter.name) == name)
+    }
+
+    /// Returns the first parameter found from the given names, which is useful for
+    /// parameters with aliases (e.g. `image`, then `image_name`).
+    pub fn get_any(&self, names: &[&str]) -> Option<&'a TemplateParameter> {
+        names.iter().find_map(|name| self.get(name))
+    }
+
+    /// Returns the positional parameter at the given index, starting from 1.
+    ///
+    /// This includes explicitly numbered parameters (e.g. `|2=`).
+    pub fn get_positional(&self, index: usize) -> Option<&'a TemplateParameter> {
+        self.get(&index.to_string())
+    }
+
+    /// Returns the positional parameters in order of their index, including explicitly
+    /// numbered parameters.
+    pub fn positional(&self) -> impl Iterator<Item = &'a TemplateParameter> {
+        let mut positional = self
+            .parameters
+            .iter()
+            .filter_map(|parameter| Some((parameter.name.parse::<usize>().ok()?, parameter)))
+            .collect::<Vec<_>>();
+        positional.sort_by_key(|(index, _)| *index);
+        positional.into_iter().map(|(_, parameter)| parameter)
+    }
+
+    /// Returns the simplified value of the parameter with the given name, ignoring case
+    /// and whitespace.
+    pub fn value(&self, name: &str) -> Option<&'a [Spanned<WikitextSimplifiedNode>]> {
+        self.get(name).map(|parameter| parameter.value.as_slice())
+    }
+
+    /// Returns the value of the parameter with the given name as it appears in the source,
+    /// ignoring case and whitespace in the name.
+    pub fn raw_value(&self, name: &str) -> Option<&'a str> {
+        self.get(name).map(|parameter| parameter.raw_value.as_str())
+    }
+}
+
+impl WikitextSimplifiedNode {
+    /// Returns a [`TemplateRef`] view over this node if it is a template.
+    pub fn as_template(&self) -> Option<TemplateRef<'_>> {
+        match self {
+            Self::Template {
+                name,
+                normalized_name,
+                name_span,
+                parameters,
+            } => Some(TemplateRef {
+                name,
+                normalized_name,
+                name_span: *name_span,
+                parameters,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -19,6 +19,22 @@ fn sp(value: WSN, start: usize, end: usize) -> Spanned<WSN> {
     }
 }
 
+// Helper function to create a TagAttribute with specific span
+fn attr(
+    name: &str,
+    value: Option<&str>,
+    quote: AttributeQuote,
+    start: usize,
+    end: usize,
+) -> TagAttribute {
+    TagAttribute {
+        name: name.into(),
+        value: value.map(Into::into),
+        quote,
+        span: Span { start, end },
+    }
+}
+
 #[test]
 fn test_s_after_link() {
     let wikitext = "cool [[thing]]s by cool [[Person|person]]s";
@@ -35,8 +51,18 @@ fn test_s_after_link() {
             ),
             sp(
                 WSN::Link {
-                    text: "things".into(),
-                    title: "thing".into()
+                    text: vec![
+                        sp(
+                            WSN::Text {
+                                text: "thing".into()
+                            },
+                            7,
+                            12
+                        ),
+                        sp(WSN::Text { text: "s".into() }, 14, 15),
+                    ],
+                    title: "thing".into(),
+                    target: LinkTarget::parse("thing"),
                 },
                 5,
                 15
@@ -50,8 +76,18 @@ fn test_s_after_link() {
             ),
             sp(
                 WSN::Link {
-                    text: "persons".into(),
-                    title: "Person".into()
+                    text: vec![
+                        sp(
+                            WSN::Text {
+                                text: "person".into()
+                            },
+                            33,
+                            39
+                        ),
+                        sp(WSN::Text { text: "s".into() }, 41, 42),
+                    ],
+                    title: "Person".into(),
+                    target: LinkTarget::parse("Person"),
                 },
                 24,
                 42
@@ -68,8 +104,49 @@ fn can_parse_wikitext_in_link() {
         simplified,
         vec![sp(
             WSN::Link {
-                text: "{{music|time|4|4}}".into(),
-                title: "Time signature".into()
+                text: vec![sp(
+                    WSN::Template {
+                        name: "music".into(),
+                        normalized_name: TemplateName::parse("music"),
+                        name_span: Span { start: 19, end: 24 },
+                        parameters: vec![
+                            TemplateParameter {
+                                name: "1".into(),
+                                value: vec![sp(
+                                    WSN::Text {
+                                        text: "time".into()
+                                    },
+                                    25,
+                                    29
+                                )],
+                                raw_value: "time".into(),
+                                span: Span { start: 25, end: 29 },
+                                name_span: None,
+                                value_span: Span { start: 25, end: 29 },
+                            },
+                            TemplateParameter {
+                                name: "2".into(),
+                                value: vec![sp(WSN::Text { text: "4".into() }, 30, 31)],
+                                raw_value: "4".into(),
+                                span: Span { start: 30, end: 31 },
+                                name_span: None,
+                                value_span: Span { start: 30, end: 31 },
+                            },
+                            TemplateParameter {
+                                name: "3".into(),
+                                value: vec![sp(WSN::Text { text: "4".into() }, 32, 33)],
+                                raw_value: "4".into(),
+                                span: Span { start: 32, end: 33 },
+                                name_span: None,
+                                value_span: Span { start: 32, end: 33 },
+                            },
+                        ],
+                    },
+                    17,
+                    35
+                )],
+                title: "Time signature".into(),
+                target: LinkTarget::parse("Time signature"),
             },
             0,
             37
@@ -78,10 +155,57 @@ fn can_parse_wikitext_in_link() {
 }
 
 #[test]
-fn will_gracefully_ignore_refs() {
+fn can_parse_ref_with_template() {
     let wikitext = r#"<ref name=bigtakeover>{{cite web|author=Kristen Sollee|title=Japanese Rock on NPR|work=[[The Big Takeover]]|date=2006-06-25|url=http://www.bigtakeover.com/news/japanese-rock-on-npr|access-date=2013-06-07|quote=It's a style of dress, there's a lot of costuming and make up and it's uniquely Japanese because it goes back to ancient Japan. Men would often wear women's clothing...}}</ref>"#;
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
-    assert_eq!(simplified, vec![]);
+
+    let [Spanned {
+        value: WSN::Reference {
+            name,
+            group,
+            children,
+        },
+        span,
+    }] = simplified.as_slice()
+    else {
+        panic!("expected a single reference, got {simplified:?}");
+    };
+    assert_eq!(name.as_deref(), Some("bigtakeover"));
+    assert_eq!(*group, None);
+    assert_eq!(*span, Span { start: 0, end: 386 });
+
+    let [Spanned {
+        value: WSN::Template {
+            name, parameters, ..
+        },
+        span,
+    }] = children.as_slice()
+    else {
+        panic!("expected a single template, got {children:?}");
+    };
+    assert_eq!(name, "cite web");
+    assert_eq!(
+        *span,
+        Span {
+            start: 22,
+            end: 380
+        }
+    );
+    assert_eq!(
+        parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.raw_value.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("author", "Kristen Sollee"),
+            ("title", "Japanese Rock on NPR"),
+            ("work", "[[The Big Takeover]]"),
+            ("date", "2006-06-25"),
+            ("url", "http://www.bigtakeover.com/news/japanese-rock-on-npr"),
+            ("access-date", "2013-06-07"),
+            ("quote", "It's a style of dress, there's a lot of costuming and make up and it's uniquely Japanese because it goes back to ancient Japan. Men would often wear women's clothing..."),
+        ]
+    );
 }
 
 #[test]
@@ -117,7 +241,14 @@ fn will_simplify_template_parameter_inside_html_tag() {
         vec![sp(
             WSN::Tag {
                 name: "span".into(),
-                attributes: Some(r#"style="color:#505050;font-size:80%""#.into()),
+                attributes: vec![attr(
+                    "style",
+                    Some("color:#505050;font-size:80%"),
+                    AttributeQuote::Double,
+                    6,
+                    41
+                )],
+                raw_attributes: Some(r#"style="color:#505050;font-size:80%""#.into()),
                 children: vec![sp(
                     WSN::TemplateParameterUse {
                         name: "1".into(),
@@ -156,6 +287,186 @@ fn can_parse_heading() {
     );
 }
 
+#[test]
+fn test_section_tree() {
+    let wikitext =
+        "Lead\n== A ==\nIn A\n=== A1 ===\nIn A1\n==== A1a ====\n=== A2 ===\n== B ==\nIn B";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let sections = collect_sections(wikitext, &simplified);
+
+    // Each section is described by its index, level, heading and source, with its subsections
+    fn describe(wikitext: &str, section: &Section) -> String {
+        let mut description = format!(
+            "{} {} {:?} {:?}",
+            section.index,
+            section.level,
+            section.heading_text(),
+            &wikitext[section.span.start..section.span.end]
+        );
+        for subsection in &section.subsections {
+            description.push_str(&format!(" [{}]", describe(wikitext, subsection)));
+        }
+        description
+    }
+    assert_eq!(
+        sections
+            .iter()
+            .map(|section| describe(wikitext, section))
+            .collect::<Vec<_>>(),
+        vec![
+            r#"0 0 None "Lead\n""#.to_string(),
+            concat!(
+                r#"1 2 Some("A") "== A ==\nIn A\n=== A1 ===\nIn A1\n==== A1a ====\n=== A2 ===\n" "#,
+                r#"[2 3 Some("A1") "=== A1 ===\nIn A1\n==== A1a ====\n" [3 4 Some("A1a") "==== A1a ====\n"]] "#,
+                r#"[4 3 Some("A2") "=== A2 ===\n"]"#
+            )
+            .to_string(),
+            r#"5 2 Some("B") "== B ==\nIn B""#.to_string(),
+        ]
+    );
+
+    assert_eq!(
+        sections[0].children,
+        vec![sp(
+            WSN::Text {
+                text: "Lead".into()
+            },
+            0,
+            4
+        )]
+    );
+    assert_eq!(
+        find_section(&sections, 2).map(|section| section.children.len()),
+        Some(1)
+    );
+    assert_eq!(find_section(&sections, 6), None);
+}
+
+#[test]
+fn test_section_tree_with_nested_headings() {
+    let wikitext =
+        "Lead\n== A ==\n<div>\n== B ==\nx\n</div>\n{|\n|\n== C ==\n|}\n<ref>\n== R ==\n</ref>\n== D ==\ny";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let sections = collect_sections(wikitext, &simplified);
+
+    // Headings within HTML tags and tables start sections, but not those within `<ref>`s
+    let described: Vec<_> = sections
+        .iter()
+        .map(|section| {
+            (
+                section.index,
+                section.heading_text(),
+                &wikitext[section.span.start..section.span.end],
+            )
+        })
+        .collect();
+    assert_eq!(
+        described,
+        vec![
+            (0, None, "Lead\n"),
+            (1, Some("A".into()), "== A ==\n<div>\n"),
+            (2, Some("B".into()), "== B ==\nx\n</div>\n{|\n|\n"),
+            (3, Some("C".into()), "== C ==\n|}\n<ref>\n== R ==\n</ref>\n"),
+            (4, Some("D".into()), "== D ==\ny"),
+        ]
+    );
+
+    // Nodes containing headings belong to the section open where they start
+    let kinds = |index| {
+        find_section(&sections, index)
+            .unwrap()
+            .children
+            .iter()
+            .map(|node| node.value.node_type())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(kinds(1), vec!["tag"]);
+    assert_eq!(kinds(2), vec!["table"]);
+
+    // Sections are numbered in the same way when working on the source
+    for section in &sections {
+        assert_eq!(
+            get_section(wikitext, &PWT_CONFIGURATION, section.index).unwrap(),
+            Some(wikitext[section.span.start..section.span.end].trim_end())
+        );
+    }
+}
+
+#[test]
+fn test_section_tree_with_pre() {
+    let wikitext = "<pre>\n== A ==\n</pre>\n== B ==\nx";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let sections = collect_sections(wikitext, &simplified);
+
+    // Headings within `<pre>` tags do not start sections
+    assert_eq!(
+        sections
+            .iter()
+            .map(|section| (section.index, section.heading_text()))
+            .collect::<Vec<_>>(),
+        vec![(0, None), (1, Some("B".into()))]
+    );
+    assert_eq!(sections[1].span, Span { start: 21, end: 30 });
+}
+
+#[test]
+fn test_get_and_replace_section() {
+    let wikitext = "Lead\n\n== A ==\nIn A\n=== A1 ===\n{{heading|== Not a section ==}}\n<div>\n== B ==\nIn B\n</div>\n<ref>\n== Not a section ==\n</ref>\n";
+
+    let get = |index| get_section(wikitext, &PWT_CONFIGURATION, index).unwrap();
+    assert_eq!(get(0), Some("Lead"));
+    assert_eq!(
+        get(1),
+        Some("== A ==\nIn A\n=== A1 ===\n{{heading|== Not a section ==}}\n<div>")
+    );
+    assert_eq!(
+        get(2),
+        Some("=== A1 ===\n{{heading|== Not a section ==}}\n<div>")
+    );
+    assert_eq!(
+        get(3),
+        Some("== B ==\nIn B\n</div>\n<ref>\n== Not a section ==\n</ref>")
+    );
+    assert_eq!(get(4), None);
+
+    let replace =
+        |index, new_text| replace_section(wikitext, &PWT_CONFIGURATION, index, new_text).unwrap();
+    assert_eq!(
+        replace(2, "=== A2 ===\nNew"),
+        Some("Lead\n\n== A ==\nIn A\n=== A2 ===\nNew\n\n== B ==\nIn B\n</div>\n<ref>\n== Not a section ==\n</ref>".to_string())
+    );
+    assert_eq!(
+        replace(0, "New lead"),
+        Some("New lead\n\n== A ==\nIn A\n=== A1 ===\n{{heading|== Not a section ==}}\n<div>\n== B ==\nIn B\n</div>\n<ref>\n== Not a section ==\n</ref>".to_string())
+    );
+    assert_eq!(
+        replace(3, "== C =="),
+        Some(
+            "Lead\n\n== A ==\nIn A\n=== A1 ===\n{{heading|== Not a section ==}}\n<div>\n== C =="
+                .to_string()
+        )
+    );
+    assert_eq!(replace(4, "== D =="), None);
+}
+
+#[test]
+fn test_replace_section_with_pre() {
+    let wikitext = "Lead\n== A ==\n<pre>\n== Not a section ==\n</pre>\n== B ==\nIn B";
+
+    assert_eq!(
+        get_section(wikitext, &PWT_CONFIGURATION, 1).unwrap(),
+        Some("== A ==\n<pre>\n== Not a section ==\n</pre>")
+    );
+    assert_eq!(
+        replace_section(wikitext, &PWT_CONFIGURATION, 2, "== C ==").unwrap(),
+        Some("Lead\n== A ==\n<pre>\n== Not a section ==\n</pre>\n== C ==".to_string())
+    );
+    assert_eq!(
+        replace_section(wikitext, &PWT_CONFIGURATION, 3, "== C ==").unwrap(),
+        None
+    );
+}
+
 #[test]
 fn test_basic_text() {
     let wikitext = "Hello, world!";
@@ -223,9 +534,9 @@ fn test_bold_italic_text() {
     assert_eq!(
         simplified,
         vec![sp(
-            WSN::Bold {
+            WSN::Italic {
                 children: vec![sp(
-                    WSN::Italic {
+                    WSN::Bold {
                         children: vec![sp(
                             WSN::Text {
                                 text: "bold italic text".into()
@@ -299,9 +610,9 @@ fn test_mixed_formatting() {
                 52
             ),
             sp(
-                WSN::Bold {
+                WSN::Italic {
                     children: vec![sp(
-                        WSN::Italic {
+                        WSN::Bold {
                             children: vec![sp(
                                 WSN::Text {
                                     text: "bold italic".into()
@@ -322,107 +633,412 @@ fn test_mixed_formatting() {
 }
 
 #[test]
-fn test_internal_link() {
-    let wikitext = "[[Main Page]]";
+fn test_line_scoped_formatting() {
+    // Formatting left open is closed at the end of its line
+    let wikitext = "'''a\nb";
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
     assert_eq!(
         simplified,
-        vec![sp(
-            WSN::Link {
-                text: "Main Page".into(),
-                title: "Main Page".into()
-            },
-            0,
-            13
-        )]
+        vec![
+            sp(
+                WSN::Bold {
+                    children: vec![sp(WSN::Text { text: "a".into() }, 3, 4)]
+                },
+                0,
+                4
+            ),
+            sp(WSN::Text { text: "\nb".into() }, 4, 6),
+        ]
     );
-}
 
-#[test]
-fn test_internal_link_with_text() {
-    let wikitext = "[[Main Page|Home]]";
+    // Four apostrophes are an apostrophe followed by bold
+    let wikitext = "''''a''''";
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
     assert_eq!(
         simplified,
-        vec![sp(
-            WSN::Link {
-                text: "Home".into(),
-                title: "Main Page".into()
-            },
-            0,
-            18
-        )]
+        vec![
+            sp(WSN::Text { text: "'".into() }, 0, 1),
+            sp(
+                WSN::Bold {
+                    children: vec![sp(WSN::Text { text: "a'".into() }, 4, 6)]
+                },
+                1,
+                9
+            ),
+        ]
     );
-}
 
-#[test]
-fn test_external_link() {
-    let wikitext = "[https://example.com]";
+    // Bold italics are nested so that whichever is closed first is the inner one
+    let wikitext = "'''''a''b'''";
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
     assert_eq!(
         simplified,
         vec![sp(
-            WSN::ExtLink {
-                link: "https://example.com".into(),
-                text: None
+            WSN::Bold {
+                children: vec![
+                    sp(
+                        WSN::Italic {
+                            children: vec![sp(WSN::Text { text: "a".into() }, 5, 6)]
+                        },
+                        0,
+                        8
+                    ),
+                    sp(WSN::Text { text: "b".into() }, 8, 9),
+                ]
             },
             0,
-            21
+            12
         )]
     );
+
+    // With an odd number of both bold and italics, a bold after a single-letter word is an
+    // apostrophe followed by italics
+    let wikitext = "Vive l'''amour'' et '''le''' vin";
+    let (simplified, diagnostics) = parse_and_simplify_wikitext_with_diagnostics(
+        wikitext,
+        &PWT_CONFIGURATION,
+        &SimplificationConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::Text {
+                    text: "Vive l'".into()
+                },
+                0,
+                7
+            ),
+            sp(
+                WSN::Italic {
+                    children: vec![sp(
+                        WSN::Text {
+                            text: "amour".into()
+                        },
+                        9,
+                        14
+                    )]
+                },
+                7,
+                16
+            ),
+            sp(
+                WSN::Text {
+                    text: " et ".into()
+                },
+                16,
+                20
+            ),
+            sp(
+                WSN::Bold {
+                    children: vec![sp(WSN::Text { text: "le".into() }, 23, 25)]
+                },
+                20,
+                28
+            ),
+            sp(
+                WSN::Text {
+                    text: " vin".into()
+                },
+                28,
+                32
+            ),
+        ]
+    );
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.kind, d.span))
+            .collect::<Vec<_>>(),
+        vec![(DiagnosticKind::UnbalancedQuotes, Span { start: 6, end: 9 })]
+    );
 }
 
 #[test]
-fn test_external_link_with_text() {
-    let wikitext = "[https://example.com Example]";
+fn test_misnested_inline_tags() {
+    let tag = |name: &str, children| WSN::Tag {
+        name: name.into(),
+        attributes: vec![],
+        raw_attributes: None,
+        children,
+    };
+
+    // Formatting closed by the closing tag of an element it was opened within is reopened
+    // after it, as browsers do
+    let wikitext = "<small>a<b>b</small>c</b>";
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
     assert_eq!(
         simplified,
-        vec![sp(
-            WSN::ExtLink {
-                link: "https://example.com".into(),
-                text: Some("Example".into())
-            },
-            0,
-            29
-        )]
+        vec![
+            sp(
+                WSN::Small {
+                    children: vec![
+                        sp(WSN::Text { text: "a".into() }, 7, 8),
+                        sp(
+                            tag("b", vec![sp(WSN::Text { text: "b".into() }, 11, 12)]),
+                            8,
+                            12
+                        ),
+                    ]
+                },
+                0,
+                20
+            ),
+            sp(
+                tag("b", vec![sp(WSN::Text { text: "c".into() }, 20, 21)]),
+                20,
+                25
+            ),
+        ]
     );
-}
 
-#[test]
-fn test_simple_template() {
-    let wikitext = "{{Template}}";
+    // Other elements are closed, and their closing tag is discarded
+    let wikitext = "'''<span>x'''</span>";
     let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
     assert_eq!(
         simplified,
         vec![sp(
-            WSN::Template {
-                name: "Template".into(),
-                parameters: vec![]
+            WSN::Bold {
+                children: vec![sp(
+                    tag("span", vec![sp(WSN::Text { text: "x".into() }, 9, 10)]),
+                    3,
+                    10
+                )]
             },
             0,
-            12
+            13
         )]
     );
-}
 
-#[test]
-fn test_template_with_parameters() {
-    let wikitext = "{{Template|param1=value1|param2=value2}}";
-    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let recover = |wikitext: &str| {
+        let (simplified, diagnostics) = parse_and_simplify_wikitext_recovering(
+            wikitext,
+            &PWT_CONFIGURATION,
+            &SimplificationConfig::default(),
+        );
+        let diagnostics = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.kind.code(), d.span))
+            .collect::<Vec<_>>();
+        (simplified, diagnostics)
+    };
+
+    // Tags are reopened after formatting they were opened within
+    let wikitext = "''a<b>b''c</b>";
     assert_eq!(
-        simplified,
-        vec![sp(
-            WSN::Template {
-                name: "Template".into(),
+        recover(wikitext),
+        (
+            vec![
+                sp(
+                    WSN::Italic {
+                        children: vec![
+                            sp(WSN::Text { text: "a".into() }, 2, 3),
+                            sp(
+                                tag("b", vec![sp(WSN::Text { text: "b".into() }, 6, 7)]),
+                                3,
+                                7
+                            ),
+                        ]
+                    },
+                    0,
+                    9
+                ),
+                sp(
+                    tag("b", vec![sp(WSN::Text { text: "c".into() }, 9, 10)]),
+                    9,
+                    14
+                ),
+            ],
+            vec![(
+                DiagnosticSeverity::Warning,
+                "misnested-formatting",
+                Span { start: 3, end: 7 }
+            )]
+        )
+    );
+
+    // Formatting closed before anything is added after it is not reopened
+    let wikitext = "<small>a<b>b</small></b>c";
+    assert_eq!(
+        recover(wikitext),
+        (
+            vec![
+                sp(
+                    WSN::Small {
+                        children: vec![
+                            sp(WSN::Text { text: "a".into() }, 7, 8),
+                            sp(
+                                tag("b", vec![sp(WSN::Text { text: "b".into() }, 11, 12)]),
+                                8,
+                                12
+                            ),
+                        ]
+                    },
+                    0,
+                    20
+                ),
+                sp(WSN::Text { text: "c".into() }, 24, 25),
+            ],
+            vec![(
+                DiagnosticSeverity::Warning,
+                "misnested-formatting",
+                Span { start: 8, end: 12 }
+            )]
+        )
+    );
+}
+
+#[test]
+fn test_internal_link() {
+    let wikitext = "[[Main Page]]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Link {
+                text: vec![sp(
+                    WSN::Text {
+                        text: "Main Page".into()
+                    },
+                    2,
+                    11
+                )],
+                title: "Main Page".into(),
+                target: LinkTarget::parse("Main Page"),
+            },
+            0,
+            13
+        )]
+    );
+}
+
+#[test]
+fn test_internal_link_with_text() {
+    let wikitext = "[[Main Page|Home]]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Link {
+                text: vec![sp(
+                    WSN::Text {
+                        text: "Home".into()
+                    },
+                    12,
+                    16
+                )],
+                title: "Main Page".into(),
+                target: LinkTarget::parse("Main Page"),
+            },
+            0,
+            18
+        )]
+    );
+}
+
+#[test]
+fn test_external_link() {
+    let wikitext = "[https://example.com]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::ExtLink {
+                link: "https://example.com".into(),
+                protocol: "https://".into(),
+                text: None,
+                bracketed: true,
+            },
+            0,
+            21
+        )]
+    );
+}
+
+#[test]
+fn test_external_link_with_text() {
+    let wikitext = "[https://example.com Example]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::ExtLink {
+                link: "https://example.com".into(),
+                protocol: "https://".into(),
+                text: Some(vec![sp(
+                    WSN::Text {
+                        text: "Example".into()
+                    },
+                    21,
+                    28
+                )]),
+                bracketed: true,
+            },
+            0,
+            29
+        )]
+    );
+}
+
+#[test]
+fn test_simple_template() {
+    let wikitext = "{{Template}}";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Template {
+                name: "Template".into(),
+                normalized_name: TemplateName::parse("Template"),
+                name_span: Span { start: 2, end: 10 },
+                parameters: vec![]
+            },
+            0,
+            12
+        )]
+    );
+}
+
+#[test]
+fn test_template_with_parameters() {
+    let wikitext = "{{Template|param1=value1|param2=value2}}";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Template {
+                name: "Template".into(),
+                normalized_name: TemplateName::parse("Template"),
+                name_span: Span { start: 2, end: 10 },
                 parameters: vec![
                     TemplateParameter {
                         name: "param1".into(),
-                        value: "value1".into()
+                        value: vec![sp(
+                            WSN::Text {
+                                text: "value1".into()
+                            },
+                            18,
+                            24
+                        )],
+                        raw_value: "value1".into(),
+                        span: Span { start: 11, end: 24 },
+                        name_span: Some(Span { start: 11, end: 17 }),
+                        value_span: Span { start: 18, end: 24 },
                     },
                     TemplateParameter {
                         name: "param2".into(),
-                        value: "value2".into()
+                        value: vec![sp(
+                            WSN::Text {
+                                text: "value2".into()
+                            },
+                            32,
+                            38
+                        )],
+                        raw_value: "value2".into(),
+                        span: Span { start: 25, end: 38 },
+                        name_span: Some(Span { start: 25, end: 31 }),
+                        value_span: Span { start: 32, end: 38 },
                     }
                 ]
             },
@@ -441,14 +1057,36 @@ fn test_template_with_unnamed_parameters() {
         vec![sp(
             WSN::Template {
                 name: "Template".into(),
+                normalized_name: TemplateName::parse("Template"),
+                name_span: Span { start: 2, end: 10 },
                 parameters: vec![
                     TemplateParameter {
                         name: "1".into(),
-                        value: "value1".into()
+                        value: vec![sp(
+                            WSN::Text {
+                                text: "value1".into()
+                            },
+                            11,
+                            17
+                        )],
+                        raw_value: "value1".into(),
+                        span: Span { start: 11, end: 17 },
+                        name_span: None,
+                        value_span: Span { start: 11, end: 17 },
                     },
                     TemplateParameter {
                         name: "2".into(),
-                        value: "value2".into()
+                        value: vec![sp(
+                            WSN::Text {
+                                text: "value2".into()
+                            },
+                            18,
+                            24
+                        )],
+                        raw_value: "value2".into(),
+                        span: Span { start: 18, end: 24 },
+                        name_span: None,
+                        value_span: Span { start: 18, end: 24 },
                     }
                 ]
             },
@@ -467,7 +1105,8 @@ fn test_html_tag() {
         vec![sp(
             WSN::Tag {
                 name: "span".into(),
-                attributes: None,
+                attributes: vec![],
+                raw_attributes: None,
                 children: vec![sp(
                     WSN::Text {
                         text: "Hello".into()
@@ -491,7 +1130,14 @@ fn test_html_tag_with_attributes() {
         vec![sp(
             WSN::Tag {
                 name: "span".into(),
-                attributes: Some("style=\"color:red\"".into()),
+                attributes: vec![attr(
+                    "style",
+                    Some("color:red"),
+                    AttributeQuote::Double,
+                    6,
+                    23
+                )],
+                raw_attributes: Some("style=\"color:red\"".into()),
                 children: vec![sp(
                     WSN::Text {
                         text: "Red text".into()
@@ -506,6 +1152,46 @@ fn test_html_tag_with_attributes() {
     );
 }
 
+#[test]
+fn test_tag_attribute_parsing() {
+    let wikitext = "<div CLASS=foo id='a&amp;b' title=\"x  \n y\" hidden class=\"bar\" =junk data-x=\"1\"y style=\"color: red>text</div>";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let WSN::Tag {
+        attributes,
+        raw_attributes,
+        ..
+    } = &simplified[0].value
+    else {
+        panic!("expected a tag, got {simplified:?}");
+    };
+    assert_eq!(
+        attributes,
+        &vec![
+            attr("class", Some("foo"), AttributeQuote::Unquoted, 5, 14),
+            attr("id", Some("a&b"), AttributeQuote::Single, 15, 27),
+            attr("title", Some("x y"), AttributeQuote::Double, 28, 42),
+            attr("hidden", None, AttributeQuote::Unquoted, 43, 49),
+            attr("class", Some("bar"), AttributeQuote::Double, 50, 61),
+            attr("data-x", Some("\"1\"y"), AttributeQuote::Unquoted, 68, 79),
+            attr("style", Some("color: red"), AttributeQuote::Double, 80, 97),
+        ]
+    );
+    // The last of any duplicate attributes is used
+    assert_eq!(
+        simplified[0]
+            .value
+            .attribute("class")
+            .and_then(|a| a.value.as_deref()),
+        Some("bar")
+    );
+    // The attributes are kept as written for round-tripping
+    assert_eq!(raw_attributes.as_deref(), Some(&wikitext[5..97]));
+    assert_eq!(
+        simplified[0].value.to_wikitext(),
+        format!("{}text</div>", &wikitext[..98])
+    );
+}
+
 #[test]
 fn test_blockquote() {
     let wikitext = "<blockquote>Quoted text</blockquote>";
@@ -694,8 +1380,18 @@ fn test_template_in_link() {
         simplified,
         vec![sp(
             WSN::Link {
-                text: "{{Template}}".into(),
-                title: "Page".into()
+                text: vec![sp(
+                    WSN::Template {
+                        name: "Template".into(),
+                        normalized_name: TemplateName::parse("Template"),
+                        name_span: Span { start: 9, end: 17 },
+                        parameters: vec![],
+                    },
+                    7,
+                    19
+                )],
+                title: "Page".into(),
+                target: LinkTarget::parse("Page"),
             },
             0,
             21
@@ -712,9 +1408,27 @@ fn test_formatting_in_template() {
         vec![sp(
             WSN::Template {
                 name: "Template".into(),
+                normalized_name: TemplateName::parse("Template"),
+                name_span: Span { start: 2, end: 10 },
                 parameters: vec![TemplateParameter {
                     name: "param".into(),
-                    value: "'''bold'''".into()
+                    value: vec![sp(
+                        WSN::Bold {
+                            children: vec![sp(
+                                WSN::Text {
+                                    text: "bold".into()
+                                },
+                                20,
+                                24
+                            )]
+                        },
+                        17,
+                        27
+                    )],
+                    raw_value: "'''bold'''".into(),
+                    span: Span { start: 11, end: 27 },
+                    name_span: Some(Span { start: 11, end: 16 }),
+                    value_span: Span { start: 17, end: 27 },
                 }]
             },
             0,
@@ -734,7 +1448,8 @@ fn test_mismatched_tags_discarded() {
         vec![sp(
             WSN::Tag {
                 name: "span".into(),
-                attributes: None,
+                attributes: vec![],
+                raw_attributes: None,
                 children: vec![sp(
                     WSN::Text {
                         text: "text".into()
@@ -872,6 +1587,135 @@ fn test_table_conversion() {
     );
 }
 
+#[test]
+fn test_table_grid() {
+    let wikitext = r#"{| class="wikitable"
+! A !! B !! C
+|-
+! rowspan="2" | X
+| colspan=2 | Wide
+|-
+| B1 || C1
+|-
+! Y
+| B3
+|}"#;
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let grid = simplified[0].value.table_grid().unwrap();
+    assert_eq!((grid.width(), grid.height()), (3, 4));
+
+    // Each slot is the text of the cell covering it and the slot the cell starts at
+    let slots: Vec<Vec<Option<(String, usize, usize)>>> = grid
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|slot| {
+                    slot.map(|cell| {
+                        let text: String = cell
+                            .cell
+                            .content
+                            .iter()
+                            .map(|node| node.value.to_plain_text())
+                            .collect();
+                        (text.trim().to_string(), cell.row, cell.column)
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    let slot = |text: &str, row, column| Some((text.to_string(), row, column));
+    assert_eq!(
+        slots,
+        vec![
+            vec![slot("A", 0, 0), slot("B", 0, 1), slot("C", 0, 2)],
+            vec![slot("X", 1, 0), slot("Wide", 1, 1), slot("Wide", 1, 1)],
+            vec![slot("X", 1, 0), slot("B1", 2, 1), slot("C1", 2, 2)],
+            vec![slot("Y", 3, 0), slot("B3", 3, 1), None],
+        ]
+    );
+
+    let x = grid.get(2, 0).unwrap();
+    assert_eq!((x.rowspan, x.colspan), (2, 1));
+    assert!(!x.is_origin(2, 0) && x.is_origin(1, 0));
+    assert_eq!(grid.header_rows(), vec![0]);
+    assert_eq!(grid.header_columns(), vec![0]);
+}
+
+#[test]
+fn test_table_export() {
+    let wikitext = r#"{| class="wikitable"
+! Name !! Year !! Year
+|-
+| rowspan="2" | Foo, "Bar"<ref>Source</ref> || 2001 || 2002{{efn|Note}}
+|-
+| 2003 || 2004<ref>Other source</ref>
+|}"#;
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let grid = simplified[0].value.table_grid().unwrap();
+    let record = |values: &[(&str, TableValue)]| TableRecord {
+        fields: values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+    };
+    let text = |text: &str| TableValue::Text(text.into());
+
+    let options = TableExportOptions::default();
+    assert_eq!(
+        grid.to_records(&options),
+        vec![
+            record(&[
+                ("Name", text("Foo, \"Bar\"")),
+                ("Year", text("2001")),
+                ("Year_2", text("2002"))
+            ]),
+            record(&[
+                ("Name", text("Foo, \"Bar\"")),
+                ("Year", text("2003")),
+                ("Year_2", text("2004"))
+            ]),
+        ]
+    );
+    assert_eq!(
+        grid.to_csv(&options),
+        "Name,Year,Year_2\n\"Foo, \"\"Bar\"\"\",2001,2002\n\"Foo, \"\"Bar\"\"\",2003,2004\n"
+    );
+    assert_eq!(
+        serde_json::to_string(&grid.to_records(&options)[1]).unwrap(),
+        r#"{"Name":"Foo, \"Bar\"","Year":"2003","Year_2":"2004"}"#
+    );
+
+    // Footnotes are numbered within the table, and spanned values are only given once
+    let options = TableExportOptions {
+        repeat_spanned_values: false,
+        strip_footnotes: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        grid.to_csv(&options),
+        "Name,Year,Year_2\n\"Foo, \"\"Bar\"\"[1]\",2001,2002\n,2003,2004[2]\n"
+    );
+
+    // The header row can be chosen, and content can be kept as nodes
+    let options = TableExportOptions {
+        header_row: Some(1),
+        cell_content: TableCellContent::Nodes,
+        ..Default::default()
+    };
+    let records = grid.to_records(&options);
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].columns().collect::<Vec<_>>(),
+        vec!["Foo, \"Bar\"", "2001", "2002"]
+    );
+    assert_eq!(
+        records[0].get("2001"),
+        Some(&TableValue::Nodes(
+            grid.get(2, 1).unwrap().cell.content.clone()
+        ))
+    );
+}
+
 #[test]
 fn test_redirect() {
     let wikitext = "#REDIRECT [[Target Page]]";
@@ -987,7 +1831,8 @@ end)
         simplified,
         vec![sp(WSN::Tag {
             name: "syntaxhighlight".into(),
-            attributes: Some("line".into()),
+            attributes: vec![attr("line", None, AttributeQuote::Unquoted, 17, 21)],
+            raw_attributes: Some("line".into()),
             children: vec![sp(
                 WSN::Text {
                     text: "\neffects = {}\n\n-- Make sure to clean up everything on ModuleUnload.\nEvents:Subscribe(\"ModuleUnload\", function()\n\tfor index, effect in ipairs(effects) do\n\t\teffect:Remove()\n\tend\nend)\n".into(),
@@ -1063,25 +1908,36 @@ fn can_handle_lists_underneath_headers() {
             }, 49, 69),
             sp(WSN::UnorderedList {
                 items: vec![WikitextSimplifiedListItem {
+                    marker: "*".into(),
                     content: vec![
                         sp(WSN::Text { text: "Shared".into() }, 73, 79),
                         sp(WSN::UnorderedList {
                             items: vec![
                                 WikitextSimplifiedListItem {
+                                    marker: "**".into(),
                                     content: vec![sp(WSN::Text {
                                         text: "Overhauled the logging system to support unicode (the first of many unicode additions to come)".into()
                                     }, 83, 177)]
                                 },
                                 WikitextSimplifiedListItem {
+                                    marker: "**".into(),
                                     content: vec![
                                         sp(WSN::Text {
                                             text: "Added console command for profiling Lua modules; usage: profiler_sample ".into()
                                         }, 181, 253),
                                         sp(WSN::Template {
                                             name: "Arg".into(),
+                                            normalized_name: TemplateName::parse("Arg"),
+                                            name_span: Span { start: 255, end: 258 },
                                             parameters: vec![TemplateParameter {
                                                 name: "1".into(),
-                                                value: "number_of_seconds".into()
+                                                value: vec![sp(WSN::Text {
+                                                    text: "number_of_seconds".into()
+                                                }, 259, 276)],
+                                                raw_value: "number_of_seconds".into(),
+                                                span: Span { start: 259, end: 276 },
+                                                name_span: None,
+                                                value_span: Span { start: 259, end: 276 },
                                             }]
                                         }, 253, 278)
                                     ]
@@ -1154,14 +2010,28 @@ fn test_to_wikitext_bold_italic() {
 #[test]
 fn test_to_wikitext_link() {
     let node = WSN::Link {
-        text: "Main Page".into(),
+        text: vec![sp(
+            WSN::Text {
+                text: "Main Page".into(),
+            },
+            0,
+            0,
+        )],
         title: "Main Page".into(),
+        target: LinkTarget::parse("Main Page"),
     };
     assert_eq!(node.to_wikitext(), "[[Main Page]]");
 
     let node = WSN::Link {
-        text: "Home".into(),
+        text: vec![sp(
+            WSN::Text {
+                text: "Home".into(),
+            },
+            0,
+            0,
+        )],
         title: "Main Page".into(),
+        target: LinkTarget::parse("Main Page"),
     };
     assert_eq!(node.to_wikitext(), "[[Main Page|Home]]");
 }
@@ -1170,35 +2040,77 @@ fn test_to_wikitext_link() {
 fn test_to_wikitext_ext_link() {
     let node = WSN::ExtLink {
         link: "https://example.com".into(),
+        protocol: "https://".into(),
         text: None,
+        bracketed: true,
     };
     assert_eq!(node.to_wikitext(), "[https://example.com]");
 
     let node = WSN::ExtLink {
         link: "https://example.com".into(),
-        text: Some("Example".into()),
+        protocol: "https://".into(),
+        text: Some(vec![sp(
+            WSN::Text {
+                text: "Example".into(),
+            },
+            0,
+            0,
+        )]),
+        bracketed: true,
     };
     assert_eq!(node.to_wikitext(), "[https://example.com Example]");
+
+    let node = WSN::ExtLink {
+        link: "https://example.com".into(),
+        protocol: "https://".into(),
+        text: None,
+        bracketed: false,
+    };
+    assert_eq!(node.to_wikitext(), "https://example.com");
 }
 
 #[test]
 fn test_to_wikitext_template() {
     let node = WSN::Template {
         name: "Template".into(),
+        normalized_name: TemplateName::parse("Template"),
+        name_span: Span { start: 0, end: 0 },
         parameters: vec![],
     };
     assert_eq!(node.to_wikitext(), "{{Template}}");
 
     let node = WSN::Template {
         name: "Template".into(),
+        normalized_name: TemplateName::parse("Template"),
+        name_span: Span { start: 0, end: 0 },
         parameters: vec![
             TemplateParameter {
                 name: "param1".into(),
-                value: "value1".into(),
+                value: vec![sp(
+                    WSN::Text {
+                        text: "value1".into(),
+                    },
+                    0,
+                    0,
+                )],
+                raw_value: "value1".into(),
+                span: Span { start: 0, end: 0 },
+                name_span: None,
+                value_span: Span { start: 0, end: 0 },
             },
             TemplateParameter {
                 name: "param2".into(),
-                value: "value2".into(),
+                value: vec![sp(
+                    WSN::Text {
+                        text: "value2".into(),
+                    },
+                    0,
+                    0,
+                )],
+                raw_value: "value2".into(),
+                span: Span { start: 0, end: 0 },
+                name_span: None,
+                value_span: Span { start: 0, end: 0 },
             },
         ],
     };
@@ -1209,14 +2121,36 @@ fn test_to_wikitext_template() {
 
     let node = WSN::Template {
         name: "Template".into(),
+        normalized_name: TemplateName::parse("Template"),
+        name_span: Span { start: 0, end: 0 },
         parameters: vec![
             TemplateParameter {
                 name: "1".into(),
-                value: "value1".into(),
+                value: vec![sp(
+                    WSN::Text {
+                        text: "value1".into(),
+                    },
+                    0,
+                    0,
+                )],
+                raw_value: "value1".into(),
+                span: Span { start: 0, end: 0 },
+                name_span: None,
+                value_span: Span { start: 0, end: 0 },
             },
             TemplateParameter {
                 name: "2".into(),
-                value: "value2".into(),
+                value: vec![sp(
+                    WSN::Text {
+                        text: "value2".into(),
+                    },
+                    0,
+                    0,
+                )],
+                raw_value: "value2".into(),
+                span: Span { start: 0, end: 0 },
+                name_span: None,
+                value_span: Span { start: 0, end: 0 },
             },
         ],
     };
@@ -1242,7 +2176,8 @@ fn test_to_wikitext_heading() {
 fn test_to_wikitext_tag() {
     let node = WSN::Tag {
         name: "span".into(),
-        attributes: None,
+        attributes: vec![],
+        raw_attributes: None,
         children: vec![sp(
             WSN::Text {
                 text: "Hello".into(),
@@ -1255,7 +2190,8 @@ fn test_to_wikitext_tag() {
 
     let node = WSN::Tag {
         name: "span".into(),
-        attributes: Some("style=\"color:red\"".into()),
+        attributes: vec![],
+        raw_attributes: Some("style=\"color:red\"".into()),
         children: vec![sp(
             WSN::Text {
                 text: "Red text".into(),
@@ -1358,7 +2294,8 @@ fn test_to_wikitext_table_representative() {
                         sp(
                             WSN::Tag {
                                 name: "font".into(),
-                                attributes: Some("size=\"3\"".into()),
+                                attributes: vec![],
+                                raw_attributes: Some("size=\"3\"".into()),
                                 children: vec![sp(
                                     WSN::Text {
                                         text: "Returns".into(),
@@ -1393,7 +2330,8 @@ fn test_to_wikitext_table_representative() {
                     content: vec![sp(
                         WSN::Tag {
                             name: "font".into(),
-                            attributes: Some("size=\"3\"".into()),
+                            attributes: vec![],
+                            raw_attributes: Some("size=\"3\"".into()),
                             children: vec![sp(
                                 WSN::Text {
                                     text: "None".into(),
@@ -1418,6 +2356,7 @@ fn test_to_wikitext_list() {
     let node = WSN::OrderedList {
         items: vec![
             WikitextSimplifiedListItem {
+                marker: "#".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Item 1".into(),
@@ -1427,6 +2366,7 @@ fn test_to_wikitext_list() {
                 )],
             },
             WikitextSimplifiedListItem {
+                marker: "#".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Item 2".into(),
@@ -1442,6 +2382,7 @@ fn test_to_wikitext_list() {
     let node = WSN::UnorderedList {
         items: vec![
             WikitextSimplifiedListItem {
+                marker: "*".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Item 1".into(),
@@ -1451,6 +2392,7 @@ fn test_to_wikitext_list() {
                 )],
             },
             WikitextSimplifiedListItem {
+                marker: "*".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Item 2".into(),
@@ -1465,17 +2407,68 @@ fn test_to_wikitext_list() {
 }
 
 #[test]
-fn test_to_wikitext_redirect() {
-    let node = WSN::Redirect {
-        target: "Target Page".into(),
+fn test_nested_and_mixed_lists() {
+    let wikitext = "* a\n** b\n*# c\n*#: d\n** e\n# f\n** g";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(simplified.len(), 3);
+
+    let WSN::UnorderedList { items } = &simplified[0].value else {
+        panic!("expected an unordered list, got {simplified:?}");
     };
-    assert_eq!(node.to_wikitext(), "#REDIRECT [[Target Page]]");
-}
+    assert_eq!((items[0].marker.as_str(), items[0].depth()), ("*", 1));
+    let WSN::OrderedList { items: nested } = &items[0].content[2].value else {
+        panic!("expected a nested ordered list, got {items:?}");
+    };
+    assert_eq!((nested[0].marker.as_str(), nested[0].depth()), ("*#", 2));
+    let WSN::DefinitionList { items: nested } = &nested[0].content[1].value else {
+        panic!("expected a nested definition list, got {nested:?}");
+    };
+    assert_eq!((nested[0].marker.as_str(), nested[0].depth()), ("*#:", 3));
 
-#[test]
-fn test_to_wikitext_special_nodes() {
-    assert_eq!(WSN::HorizontalDivider.to_wikitext(), "----");
-    assert_eq!(WSN::ParagraphBreak.to_wikitext(), "<br/>");
+    // Lists are written back with the markers they were written with
+    assert_eq!(
+        simplified[0].value.to_wikitext(),
+        "*a\n**b\n*#c\n*#:d\n**e\n"
+    );
+    assert_eq!(simplified[1].value.to_wikitext(), "#f\n");
+    assert_eq!(simplified[2].value.to_wikitext(), "**g\n");
+
+    // Lists without markers take them from their nesting
+    let item = |content: WSN| WikitextSimplifiedListItem {
+        marker: String::new(),
+        content: vec![sp(content, 0, 0)],
+    };
+    let text = |text: &str| WSN::Text { text: text.into() };
+    let node = WSN::OrderedList {
+        items: vec![WikitextSimplifiedListItem {
+            marker: String::new(),
+            content: vec![
+                sp(text("a"), 0, 0),
+                sp(
+                    WSN::UnorderedList {
+                        items: vec![item(text("b")), item(text("c"))],
+                    },
+                    0,
+                    0,
+                ),
+            ],
+        }],
+    };
+    assert_eq!(node.to_wikitext(), "#a\n#*b\n#*c\n");
+}
+
+#[test]
+fn test_to_wikitext_redirect() {
+    let node = WSN::Redirect {
+        target: "Target Page".into(),
+    };
+    assert_eq!(node.to_wikitext(), "#REDIRECT [[Target Page]]");
+}
+
+#[test]
+fn test_to_wikitext_special_nodes() {
+    assert_eq!(WSN::HorizontalDivider.to_wikitext(), "----");
+    assert_eq!(WSN::ParagraphBreak.to_wikitext(), "<br/>");
     assert_eq!(WSN::Newline.to_wikitext(), "\n");
 }
 
@@ -1613,6 +2606,7 @@ fn test_definition_list() {
                 items: vec![
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Term,
+                        marker: ";".into(),
                         content: vec![sp(
                             WSN::Text {
                                 text: "Term 1".into()
@@ -1623,6 +2617,7 @@ fn test_definition_list() {
                     },
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Details,
+                        marker: ":".into(),
                         content: vec![sp(
                             WSN::Text {
                                 text: "Definition 1".into()
@@ -1633,6 +2628,7 @@ fn test_definition_list() {
                     },
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Term,
+                        marker: ";".into(),
                         content: vec![sp(
                             WSN::Text {
                                 text: "Term 2".into()
@@ -1643,6 +2639,7 @@ fn test_definition_list() {
                     },
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Details,
+                        marker: ":".into(),
                         content: vec![sp(
                             WSN::Text {
                                 text: "Definition 2".into()
@@ -1665,6 +2662,7 @@ fn test_definition_list_to_wikitext() {
         items: vec![
             WikitextSimplifiedDefinitionListItem {
                 type_: DefinitionListItemType::Term,
+                marker: ";".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Term 1".into(),
@@ -1675,6 +2673,7 @@ fn test_definition_list_to_wikitext() {
             },
             WikitextSimplifiedDefinitionListItem {
                 type_: DefinitionListItemType::Details,
+                marker: ":".into(),
                 content: vec![sp(
                     WSN::Text {
                         text: "Definition 1".into(),
@@ -1700,6 +2699,7 @@ fn test_definition_list_with_formatting() {
                 items: vec![
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Term,
+                        marker: ";".into(),
                         content: vec![sp(
                             WSN::Bold {
                                 children: vec![sp(
@@ -1716,6 +2716,7 @@ fn test_definition_list_with_formatting() {
                     },
                     WikitextSimplifiedDefinitionListItem {
                         type_: DefinitionListItemType::Details,
+                        marker: ":".into(),
                         content: vec![sp(
                             WSN::Italic {
                                 children: vec![sp(
@@ -1738,6 +2739,43 @@ fn test_definition_list_with_formatting() {
     );
 }
 
+#[test]
+fn test_definition_list_entries() {
+    let wikitext = ";Apple:A fruit\n:Also a company\n;Banana\n;Cherry\n:Red\n:Small";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let text = |text: &str, start, end| vec![sp(WSN::Text { text: text.into() }, start, end)];
+    assert_eq!(
+        simplified[0].value.definition_list_entries().unwrap(),
+        vec![
+            DefinitionListEntry {
+                term: Some(text("Apple", 1, 6)),
+                details: vec![text("A fruit", 7, 14), text("Also a company", 16, 30)],
+            },
+            DefinitionListEntry {
+                term: Some(text("Banana", 32, 38)),
+                details: vec![],
+            },
+            DefinitionListEntry {
+                term: Some(text("Cherry", 40, 46)),
+                details: vec![text("Red", 48, 51), text("Small", 53, 58)],
+            },
+        ]
+    );
+
+    // Indentation on talk pages is details without a term
+    let wikitext = ":Reply\n:Another reply";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let entries = simplified[0].value.definition_list_entries().unwrap();
+    assert_eq!(
+        entries,
+        vec![DefinitionListEntry {
+            term: None,
+            details: vec![text("Reply", 1, 6), text("Another reply", 8, 21)],
+        }]
+    );
+    assert!(entries[0].is_indentation());
+}
+
 #[test]
 fn test_mapframe_with_json_content_and_stray_closing_div() {
     // This wikitext has a stray </div> closing tag with no matching opener.
@@ -1793,7 +2831,8 @@ In 2023, ''[[Rolling Stone]]'' described Michigan rap as "the regional style of
         vec![
             sp(WSN::Tag {
                 name: "mapframe".into(),
-                attributes: Some("service=\"leaflet\"\n    stroke-width=\"2\"\n    fill=\"#808080\"\n    fill-opacity=\"0.5\"\n    zoom=\"8\"\n    width=\"350\"\n    height=\"350\"\n    align=\"center\"".into()),
+                attributes: vec![attr("service", Some("leaflet"), AttributeQuote::Double, 14, 31), attr("stroke-width", Some("2"), AttributeQuote::Double, 36, 52), attr("fill", Some("#808080"), AttributeQuote::Double, 57, 71), attr("fill-opacity", Some("0.5"), AttributeQuote::Double, 76, 94), attr("zoom", Some("8"), AttributeQuote::Double, 99, 107), attr("width", Some("350"), AttributeQuote::Double, 112, 123), attr("height", Some("350"), AttributeQuote::Double, 128, 140), attr("align", Some("center"), AttributeQuote::Double, 145, 159)],
+                raw_attributes: Some("service=\"leaflet\"\n    stroke-width=\"2\"\n    fill=\"#808080\"\n    fill-opacity=\"0.5\"\n    zoom=\"8\"\n    width=\"350\"\n    height=\"350\"\n    align=\"center\"".into()),
                 children: vec![
                     sp(WSN::Text {
                         text: "\n  {\n    \"type\": \"FeatureCollection\",\n    \"features\": [\n      {\n        \"type\": \"Feature\",\n        \"geometry\": {\n          \"type\": \"Point\",\n          \"coordinates\": [-83.693333, 43.018889]\n        },\n        \"properties\": {\n          \"title\": \"Flint\",\n          \"id\": \"Q490584\"\n        }\n      },\n      {\n        \"type\": \"Feature\",\n        \"geometry\": {\n          \"type\": \"Point\",\n          \"coordinates\": [-83.0458, 42.3314]\n        },\n        \"properties\": {\n          \"title\": \"Detroit\",\n          \"id\": \"Q5879\",\n          \"fill\": \"#0000FF\",\n          \"fill-opacity\": 0.5\n        }\n      }\n    ]\n  }\n  ".into()
@@ -1805,7 +2844,8 @@ In 2023, ''[[Rolling Stone]]'' described Michigan rap as "the regional style of
                     sp(WSN::Text { text: " ".into() }, 781, 782),
                     sp(WSN::Tag {
                         name: "div".into(),
-                        attributes: Some("style=\"text-align: center; font-style: italic;\"".into()),
+                        attributes: vec![attr("style", Some("text-align: center; font-style: italic;"), AttributeQuote::Double, 787, 834)],
+                        raw_attributes: Some("style=\"text-align: center; font-style: italic;\"".into()),
                         children: vec![
                             sp(WSN::Text {
                                 text: "Interactive map, Flint and Detroit points".into()
@@ -1822,27 +2862,29 @@ In 2023, ''[[Rolling Stone]]'' described Michigan rap as "the regional style of
             }, 890, 908),
             sp(WSN::Text { text: " is a subgenre of ".into() }, 908, 926),
             sp(WSN::Link {
-                text: "Midwestern hip-hop".into(),
+                text: vec![sp(WSN::Text { text: "Midwestern hip-hop".into() }, 928, 946)],
                 title: "Midwestern hip-hop".into(),
+                target: LinkTarget::parse("Midwestern hip-hop"),
             }, 926, 948),
             sp(WSN::Text {
                 text: " in the United States, including Detroit rap, Flint rap, and a style characterized by its \"underdog spirit.\" Pivotal figures in the emergence of Michigan and Midwestern hip-hop scene include ".into()
             }, 948, 1139),
-            sp(WSN::Link { text: "J Dilla".into(), title: "J Dilla".into() }, 1139, 1150),
+            sp(WSN::Link { text: vec![sp(WSN::Text { text: "J Dilla".into() }, 1141, 1148)], title: "J Dilla".into(), target: LinkTarget::parse("J Dilla") }, 1139, 1150),
             sp(WSN::Text { text: ", ".into() }, 1150, 1152),
-            sp(WSN::Link { text: "Eminem".into(), title: "Eminem".into() }, 1152, 1162),
+            sp(WSN::Link { text: vec![sp(WSN::Text { text: "Eminem".into() }, 1154, 1160)], title: "Eminem".into(), target: LinkTarget::parse("Eminem") }, 1152, 1162),
             sp(WSN::Text { text: ", ".into() }, 1162, 1164),
-            sp(WSN::Link { text: "MC Breed".into(), title: "MC Breed".into() }, 1164, 1176),
+            sp(WSN::Link { text: vec![sp(WSN::Text { text: "MC Breed".into() }, 1166, 1174)], title: "MC Breed".into(), target: LinkTarget::parse("MC Breed") }, 1164, 1176),
             sp(WSN::Text { text: ", and ".into() }, 1176, 1182),
-            sp(WSN::Link { text: "Big Sean".into(), title: "Big Sean".into() }, 1182, 1194),
+            sp(WSN::Link { text: vec![sp(WSN::Text { text: "Big Sean".into() }, 1184, 1192)], title: "Big Sean".into(), target: LinkTarget::parse("Big Sean") }, 1182, 1194),
             sp(WSN::Text { text: ".".into() }, 1194, 1195),
             sp(WSN::ParagraphBreak, 1195, 1197),
             sp(WSN::Text { text: "In 2023, ".into() }, 1197, 1206),
             sp(WSN::Italic {
                 children: vec![
                     sp(WSN::Link {
-                        text: "Rolling Stone".into(),
+                        text: vec![sp(WSN::Text { text: "Rolling Stone".into() }, 1210, 1223)],
                         title: "Rolling Stone".into(),
+                        target: LinkTarget::parse("Rolling Stone"),
                     }, 1208, 1225),
                 ],
             }, 1206, 1227),
@@ -1852,3 +2894,1545 @@ In 2023, ''[[Rolling Stone]]'' described Michigan rap as "the regional style of
         ]
     );
 }
+
+#[test]
+fn test_image_with_options_and_caption() {
+    let wikitext = "[[File:Example.jpg|thumb|200px|left|alt=A cat|A ''cute'' cat]]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Image {
+                file: "File:Example.jpg".into(),
+                options: ImageOptions {
+                    format: Some(ImageFormat::Thumbnail),
+                    size: Some(ImageSize {
+                        width: Some(200),
+                        height: None
+                    }),
+                    alignment: Some(ImageAlignment::Left),
+                    alt: Some("A cat".into()),
+                    ..Default::default()
+                },
+                caption: vec![
+                    sp(WSN::Text { text: "A ".into() }, 46, 48),
+                    sp(
+                        WSN::Italic {
+                            children: vec![sp(
+                                WSN::Text {
+                                    text: "cute".into()
+                                },
+                                50,
+                                54
+                            )]
+                        },
+                        48,
+                        56
+                    ),
+                    sp(
+                        WSN::Text {
+                            text: " cat".into()
+                        },
+                        56,
+                        60
+                    ),
+                ],
+            },
+            0,
+            62
+        )]
+    );
+}
+
+#[test]
+fn test_image_without_caption() {
+    let wikitext = "[[File:Example.png|frameless|upright]]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Image {
+                file: "File:Example.png".into(),
+                options: ImageOptions {
+                    format: Some(ImageFormat::Frameless),
+                    upright: Some("".into()),
+                    ..Default::default()
+                },
+                caption: vec![],
+            },
+            0,
+            38
+        )]
+    );
+}
+
+#[test]
+fn test_image_options() {
+    assert_eq!(
+        ImageSize::parse("200x100px"),
+        Some(ImageSize {
+            width: Some(200),
+            height: Some(100)
+        })
+    );
+    assert_eq!(
+        ImageSize::parse("x100px"),
+        Some(ImageSize {
+            width: None,
+            height: Some(100)
+        })
+    );
+    assert_eq!(ImageSize::parse("px"), None);
+    assert_eq!(ImageSize::parse("200"), None);
+
+    let mut options = ImageOptions::default();
+    assert!(options.apply("frame"));
+    assert!(options.apply(" centre "));
+    assert!(options.apply("upright=1.5"));
+    assert!(options.apply("link="));
+    assert!(!options.apply("A caption"));
+    assert_eq!(
+        options,
+        ImageOptions {
+            format: Some(ImageFormat::Frame),
+            alignment: Some(ImageAlignment::Center),
+            upright: Some("1.5".into()),
+            link: Some("".into()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_to_wikitext_image() {
+    let node = WSN::Image {
+        file: "File:Example.jpg".into(),
+        options: ImageOptions::default(),
+        caption: vec![],
+    };
+    assert_eq!(node.to_wikitext(), "[[File:Example.jpg]]");
+
+    let node = WSN::Image {
+        file: "File:Example.jpg".into(),
+        options: ImageOptions {
+            format: Some(ImageFormat::Thumbnail),
+            size: Some(ImageSize {
+                width: Some(200),
+                height: None,
+            }),
+            alignment: Some(ImageAlignment::Right),
+            alt: Some("A cat".into()),
+            ..Default::default()
+        },
+        caption: vec![sp(
+            WSN::Text {
+                text: "A cat".into(),
+            },
+            0,
+            0,
+        )],
+    };
+    assert_eq!(
+        node.to_wikitext(),
+        "[[File:Example.jpg|thumb|right|200px|alt=A cat|A cat]]"
+    );
+}
+
+#[test]
+fn test_categories() {
+    let wikitext = "Hello[[Category:Foo|Bar]][[Category:Baz]]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::Text {
+                    text: "Hello".into()
+                },
+                0,
+                5
+            ),
+            sp(
+                WSN::Category {
+                    name: "Foo".into(),
+                    sort_key: Some("Bar".into())
+                },
+                5,
+                25
+            ),
+            sp(
+                WSN::Category {
+                    name: "Baz".into(),
+                    sort_key: None
+                },
+                25,
+                41
+            ),
+        ]
+    );
+    assert_eq!(
+        collect_categories(&simplified),
+        vec![
+            Spanned {
+                value: PageCategory {
+                    name: "Foo".into(),
+                    sort_key: Some("Bar".into())
+                },
+                span: Span { start: 5, end: 25 }
+            },
+            Spanned {
+                value: PageCategory {
+                    name: "Baz".into(),
+                    sort_key: None
+                },
+                span: Span { start: 25, end: 41 }
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_collect_nested_categories() {
+    let nodes = vec![sp(
+        WSN::Bold {
+            children: vec![sp(
+                WSN::Category {
+                    name: "Nested".into(),
+                    sort_key: None,
+                },
+                3,
+                22,
+            )],
+        },
+        0,
+        25,
+    )];
+    assert_eq!(
+        collect_categories(&nodes),
+        vec![Spanned {
+            value: PageCategory {
+                name: "Nested".into(),
+                sort_key: None
+            },
+            span: Span { start: 3, end: 22 }
+        }]
+    );
+}
+
+#[test]
+fn test_collect_within_template_parameters() {
+    let wikitext = "{{Infobox|x=A<ref>Src</ref>|y=[[Category:Foo]]}}B<ref>Other</ref>";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+
+    assert_eq!(
+        collect_categories(&simplified),
+        vec![Spanned {
+            value: PageCategory {
+                name: "Foo".into(),
+                sort_key: None
+            },
+            span: Span { start: 30, end: 46 }
+        }]
+    );
+
+    // The reference in the infobox is numbered before the one after it
+    let footnotes = collect_footnotes(&simplified);
+    assert_eq!(footnotes.len(), 1);
+    assert_eq!(
+        footnotes[0]
+            .footnotes
+            .iter()
+            .map(|footnote| (footnote.number, footnote.uses.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, vec![Span { start: 13, end: 27 }]),
+            (2, vec![Span { start: 49, end: 65 }]),
+        ]
+    );
+}
+
+#[test]
+fn test_to_wikitext_category() {
+    let node = WSN::Category {
+        name: "Foo".into(),
+        sort_key: None,
+    };
+    assert_eq!(node.to_wikitext(), "[[Category:Foo]]");
+
+    let node = WSN::Category {
+        name: "Foo".into(),
+        sort_key: Some("Bar".into()),
+    };
+    assert_eq!(node.to_wikitext(), "[[Category:Foo|Bar]]");
+}
+
+#[test]
+fn test_comments_are_dropped_by_default() {
+    let wikitext = "Hello<!-- do not change -->world";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::Text {
+                    text: "Hello".into()
+                },
+                0,
+                5
+            ),
+            sp(
+                WSN::Text {
+                    text: "world".into()
+                },
+                27,
+                32
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_comments_are_preserved_with_config() {
+    let wikitext = "Hello<!-- do not change -->world";
+    let simplified = parse_and_simplify_wikitext_with_config(
+        wikitext,
+        &PWT_CONFIGURATION,
+        &SimplificationConfig {
+            preserve_comments: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::Text {
+                    text: "Hello".into()
+                },
+                0,
+                5
+            ),
+            sp(
+                WSN::Comment {
+                    text: " do not change ".into()
+                },
+                5,
+                27
+            ),
+            sp(
+                WSN::Text {
+                    text: "world".into()
+                },
+                27,
+                32
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_to_wikitext_comment() {
+    let node = WSN::Comment {
+        text: " do not change ".into(),
+    };
+    assert_eq!(node.to_wikitext(), "<!-- do not change -->");
+}
+
+#[test]
+fn test_visit_skips_comments_by_default() {
+    let node = WSN::Bold {
+        children: vec![
+            sp(
+                WSN::Comment {
+                    text: "note".into(),
+                },
+                0,
+                0,
+            ),
+            sp(
+                WSN::Text {
+                    text: "bold".into(),
+                },
+                0,
+                0,
+            ),
+        ],
+    };
+
+    let mut visited = vec![];
+    node.visit(&mut |node| visited.push(node.node_type()));
+    assert_eq!(visited, vec!["bold", "text"]);
+
+    let mut visited = vec![];
+    node.visit_with_config(
+        &VisitConfig {
+            include_comments: true,
+        },
+        &mut |node| visited.push(node.node_type()),
+    );
+    assert_eq!(visited, vec!["bold", "comment", "text"]);
+
+    // Comments are left unchanged when they are not visited
+    let uppercase = |node: &WSN| match node {
+        WSN::Text { text } => WSN::Text {
+            text: text.to_uppercase(),
+        },
+        WSN::Comment { text } => WSN::Comment {
+            text: text.to_uppercase(),
+        },
+        _ => node.clone(),
+    };
+    let mut replaced = node.clone();
+    replaced.visit_and_replace_mut(&mut |node| uppercase(node));
+    assert_eq!(replaced.to_wikitext(), "'''<!--note-->BOLD'''");
+
+    let mut replaced = node.clone();
+    replaced.visit_and_replace_mut_with_config(
+        &VisitConfig {
+            include_comments: true,
+        },
+        &mut |node| uppercase(node),
+    );
+    assert_eq!(replaced.to_wikitext(), "'''<!--NOTE-->BOLD'''");
+}
+
+#[test]
+fn test_references() {
+    let wikitext = r#"A<ref name="a">Foo</ref>B<ref name=a /><references group='note' />"#;
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(WSN::Text { text: "A".into() }, 0, 1),
+            sp(
+                WSN::Reference {
+                    name: Some("a".into()),
+                    group: None,
+                    children: vec![sp(WSN::Text { text: "Foo".into() }, 15, 18)],
+                },
+                1,
+                24
+            ),
+            sp(WSN::Text { text: "B".into() }, 24, 25),
+            sp(
+                WSN::Reference {
+                    name: Some("a".into()),
+                    group: None,
+                    children: vec![],
+                },
+                25,
+                39
+            ),
+            sp(
+                WSN::ReferenceList {
+                    group: Some("note".into()),
+                    children: vec![],
+                },
+                39,
+                66
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_to_wikitext_references() {
+    let reference = WSN::Reference {
+        name: Some("a".into()),
+        group: Some("note".into()),
+        children: vec![sp(WSN::Text { text: "Foo".into() }, 0, 0)],
+    };
+    assert_eq!(
+        reference.to_wikitext(),
+        r#"<ref name="a" group="note">Foo</ref>"#
+    );
+
+    let reuse = WSN::Reference {
+        name: Some("a".into()),
+        group: None,
+        children: vec![],
+    };
+    assert_eq!(reuse.to_wikitext(), r#"<ref name="a" />"#);
+
+    let list = WSN::ReferenceList {
+        group: None,
+        children: vec![],
+    };
+    assert_eq!(list.to_wikitext(), "<references />");
+}
+
+#[test]
+fn test_collect_footnotes() {
+    fn reference(
+        name: Option<&str>,
+        group: Option<&str>,
+        text: &str,
+        start: usize,
+    ) -> Spanned<WSN> {
+        sp(
+            WSN::Reference {
+                name: name.map(Into::into),
+                group: group.map(Into::into),
+                children: if text.is_empty() {
+                    vec![]
+                } else {
+                    vec![sp(WSN::Text { text: text.into() }, start, start)]
+                },
+            },
+            start,
+            start + 1,
+        )
+    }
+    let nodes = vec![
+        // Used before its definition in the reference list
+        reference(Some("later"), None, "", 0),
+        reference(None, None, "Anonymous", 1),
+        reference(Some("later"), None, "", 2),
+        reference(None, Some("note"), "A note", 3),
+        sp(
+            WSN::ReferenceList {
+                group: None,
+                children: vec![reference(Some("later"), None, "Later", 4)],
+            },
+            4,
+            5,
+        ),
+        // Numbering restarts after the list
+        reference(None, None, "After", 6),
+    ];
+
+    assert_eq!(
+        collect_footnotes(&nodes),
+        vec![
+            FootnoteList {
+                group: None,
+                span: Some(Span { start: 4, end: 5 }),
+                footnotes: vec![
+                    Footnote {
+                        number: 1,
+                        name: Some("later".into()),
+                        content: vec![sp(
+                            WSN::Text {
+                                text: "Later".into()
+                            },
+                            4,
+                            4
+                        )],
+                        uses: vec![Span { start: 0, end: 1 }, Span { start: 2, end: 3 }],
+                    },
+                    Footnote {
+                        number: 2,
+                        name: None,
+                        content: vec![sp(
+                            WSN::Text {
+                                text: "Anonymous".into()
+                            },
+                            1,
+                            1
+                        )],
+                        uses: vec![Span { start: 1, end: 2 }],
+                    },
+                ],
+            },
+            FootnoteList {
+                group: Some("note".into()),
+                span: None,
+                footnotes: vec![Footnote {
+                    number: 1,
+                    name: None,
+                    content: vec![sp(
+                        WSN::Text {
+                            text: "A note".into()
+                        },
+                        3,
+                        3
+                    )],
+                    uses: vec![Span { start: 3, end: 4 }],
+                }],
+            },
+            FootnoteList {
+                group: None,
+                span: None,
+                footnotes: vec![Footnote {
+                    number: 1,
+                    name: None,
+                    content: vec![sp(
+                        WSN::Text {
+                            text: "After".into()
+                        },
+                        6,
+                        6
+                    )],
+                    uses: vec![Span { start: 6, end: 7 }],
+                }],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_nowiki() {
+    let wikitext = "A<nowiki>[[not a link]]</nowiki>B";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(WSN::Text { text: "A".into() }, 0, 1),
+            sp(
+                WSN::Nowiki {
+                    text: "[[not a link]]".into()
+                },
+                1,
+                32
+            ),
+            sp(WSN::Text { text: "B".into() }, 32, 33),
+        ]
+    );
+}
+
+#[test]
+fn test_to_wikitext_nowiki() {
+    let markup = WSN::Nowiki {
+        text: "[[not a link]]".into(),
+    };
+    assert_eq!(markup.to_wikitext(), "<nowiki>[[not a link]]</nowiki>");
+
+    let list = WSN::Nowiki {
+        text: "* not a list".into(),
+    };
+    assert_eq!(list.to_wikitext(), "<nowiki>* not a list</nowiki>");
+
+    // Plain text keeps its tag, as it may be markup when joined with the text around it
+    let link_trail = WSN::Fragment {
+        children: vec![
+            sp(
+                WSN::Link {
+                    text: vec![sp(WSN::Text { text: "X".into() }, 2, 3)],
+                    title: "X".into(),
+                    target: LinkTarget::parse("X"),
+                },
+                0,
+                5,
+            ),
+            sp(WSN::Nowiki { text: "s".into() }, 5, 22),
+        ],
+    };
+    assert_eq!(link_trail.to_wikitext(), "[[X]]<nowiki>s</nowiki>");
+
+    let apostrophes = WSN::Fragment {
+        children: vec![
+            sp(WSN::Text { text: "'".into() }, 0, 1),
+            sp(WSN::Nowiki { text: "'".into() }, 1, 18),
+            sp(WSN::Text { text: "'".into() }, 18, 19),
+        ],
+    };
+    assert_eq!(apostrophes.to_wikitext(), "'<nowiki>'</nowiki>'");
+
+    let empty = WSN::Nowiki { text: "".into() };
+    assert_eq!(empty.to_wikitext(), "<nowiki/>");
+}
+
+#[test]
+fn test_gallery() {
+    let wikitext =
+        "<gallery mode=\"packed\">\nFile:A.jpg|''Italic'' caption\nB.png|alt=Bee|100px\n</gallery>";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Gallery {
+                attributes: vec![attr("mode", Some("packed"), AttributeQuote::Double, 9, 22)],
+                raw_attributes: Some("mode=\"packed\"".into()),
+                entries: vec![
+                    WikitextSimplifiedGalleryEntry {
+                        file: "File:A.jpg".into(),
+                        options: ImageOptions::default(),
+                        caption: vec![
+                            sp(
+                                WSN::Italic {
+                                    children: vec![sp(
+                                        WSN::Text {
+                                            text: "Italic".into()
+                                        },
+                                        37,
+                                        43
+                                    )]
+                                },
+                                35,
+                                45
+                            ),
+                            sp(
+                                WSN::Text {
+                                    text: " caption".into()
+                                },
+                                45,
+                                53
+                            ),
+                        ],
+                    },
+                    WikitextSimplifiedGalleryEntry {
+                        file: "File:B.png".into(),
+                        options: ImageOptions {
+                            alt: Some("Bee".into()),
+                            size: Some(ImageSize {
+                                width: Some(100),
+                                height: None
+                            }),
+                            ..Default::default()
+                        },
+                        caption: vec![],
+                    },
+                ],
+            },
+            0,
+            84
+        )]
+    );
+}
+
+#[test]
+fn test_gallery_file_namespaces() {
+    let wikitext = "<gallery>\nRatio 1:2.jpg|A\nImage:B.jpg\nfile: C.jpg\n</gallery>";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let WSN::Gallery { entries, .. } = &simplified[0].value else {
+        panic!("expected a gallery, got {simplified:?}");
+    };
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| entry.file.as_str())
+            .collect::<Vec<_>>(),
+        vec!["File:Ratio 1:2.jpg", "File:B.jpg", "File:C.jpg"]
+    );
+}
+
+#[test]
+fn test_to_wikitext_gallery() {
+    let gallery = WSN::Gallery {
+        attributes: vec![],
+        raw_attributes: Some("mode=\"packed\"".into()),
+        entries: vec![
+            WikitextSimplifiedGalleryEntry {
+                file: "File:A.jpg".into(),
+                options: ImageOptions::default(),
+                caption: vec![sp(
+                    WSN::Text {
+                        text: "Caption".into(),
+                    },
+                    0,
+                    0,
+                )],
+            },
+            WikitextSimplifiedGalleryEntry {
+                file: "File:B.png".into(),
+                options: ImageOptions {
+                    alt: Some("Bee".into()),
+                    ..Default::default()
+                },
+                caption: vec![],
+            },
+        ],
+    };
+    assert_eq!(
+        gallery.to_wikitext(),
+        "<gallery mode=\"packed\">\nFile:A.jpg|Caption\nFile:B.png|alt=Bee\n</gallery>"
+    );
+}
+
+#[test]
+fn test_behavior_switches() {
+    let wikitext = "__NOTOC__Hello__noindex__";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::BehaviorSwitch {
+                    name: "NOTOC".into()
+                },
+                0,
+                9
+            ),
+            sp(
+                WSN::Text {
+                    text: "Hello".into()
+                },
+                9,
+                14
+            ),
+            sp(
+                WSN::BehaviorSwitch {
+                    name: "NOINDEX".into()
+                },
+                14,
+                25
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_collect_behavior_switches() {
+    let nodes = vec![
+        sp(
+            WSN::BehaviorSwitch {
+                name: "NOINDEX".into(),
+            },
+            0,
+            0,
+        ),
+        sp(
+            WSN::Bold {
+                children: vec![sp(
+                    WSN::BehaviorSwitch {
+                        name: "DISAMBIG".into(),
+                    },
+                    0,
+                    0,
+                )],
+            },
+            0,
+            0,
+        ),
+        sp(
+            WSN::BehaviorSwitch {
+                name: "NOINDEX".into(),
+            },
+            0,
+            0,
+        ),
+    ];
+    assert_eq!(
+        collect_behavior_switches(&nodes),
+        ["DISAMBIG".to_string(), "NOINDEX".to_string()]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(
+        WSN::BehaviorSwitch {
+            name: "NOTOC".into()
+        }
+        .to_wikitext(),
+        "__NOTOC__"
+    );
+}
+
+#[test]
+fn test_template_spans() {
+    let wikitext = "{{Cite| date = 2020 |x}}";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let WSN::Template {
+        name_span,
+        parameters,
+        ..
+    } = &simplified[0].value
+    else {
+        panic!("Expected template, got {simplified:?}");
+    };
+    assert_eq!(*name_span, Span { start: 2, end: 6 });
+    assert_eq!(
+        parameters
+            .iter()
+            .map(|p| (p.span, p.name_span, p.value_span))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                Span { start: 7, end: 20 },
+                Some(Span { start: 8, end: 12 }),
+                Span { start: 15, end: 19 }
+            ),
+            (
+                Span { start: 21, end: 22 },
+                None,
+                Span { start: 21, end: 22 }
+            ),
+        ]
+    );
+
+    // The spans can be used to rewrite a single parameter in place
+    let date = parameters[0].value_span;
+    let rewritten = format!("{}2021{}", &wikitext[..date.start], &wikitext[date.end..]);
+    assert_eq!(rewritten, "{{Cite| date = 2021 |x}}");
+}
+
+#[test]
+fn test_template_parameter_whitespace() {
+    let wikitext = "{{t| a |b= c }}";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Template {
+                name: "t".into(),
+                normalized_name: TemplateName::parse("t"),
+                name_span: Span { start: 2, end: 3 },
+                parameters: vec![
+                    TemplateParameter {
+                        name: "1".into(),
+                        value: vec![sp(WSN::Text { text: " a ".into() }, 4, 7)],
+                        raw_value: " a ".into(),
+                        span: Span { start: 4, end: 7 },
+                        name_span: None,
+                        value_span: Span { start: 4, end: 7 },
+                    },
+                    TemplateParameter {
+                        name: "b".into(),
+                        value: vec![sp(WSN::Text { text: "c".into() }, 11, 12)],
+                        raw_value: "c".into(),
+                        span: Span { start: 8, end: 13 },
+                        name_span: Some(Span { start: 8, end: 9 }),
+                        value_span: Span { start: 11, end: 12 },
+                    },
+                ]
+            },
+            0,
+            15
+        )]
+    );
+}
+
+#[test]
+fn test_template_parameter_duplicates() {
+    let wikitext = "{{t| a |b= c |1=d|b=e}}";
+    let (simplified, diagnostics) = parse_and_simplify_wikitext_with_diagnostics(
+        wikitext,
+        &PWT_CONFIGURATION,
+        &SimplificationConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Template {
+                name: "t".into(),
+                normalized_name: TemplateName::parse("t"),
+                name_span: Span { start: 2, end: 3 },
+                parameters: vec![
+                    TemplateParameter {
+                        name: "1".into(),
+                        value: vec![sp(WSN::Text { text: "d".into() }, 16, 17)],
+                        raw_value: "d".into(),
+                        span: Span { start: 14, end: 17 },
+                        name_span: Some(Span { start: 14, end: 15 }),
+                        value_span: Span { start: 16, end: 17 },
+                    },
+                    TemplateParameter {
+                        name: "b".into(),
+                        value: vec![sp(WSN::Text { text: "e".into() }, 20, 21)],
+                        raw_value: "e".into(),
+                        span: Span { start: 18, end: 21 },
+                        name_span: Some(Span { start: 18, end: 19 }),
+                        value_span: Span { start: 20, end: 21 },
+                    },
+                ]
+            },
+            0,
+            23
+        )]
+    );
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.kind, d.span))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                DiagnosticSeverity::Warning,
+                DiagnosticKind::DuplicateTemplateParameter,
+                Span { start: 4, end: 7 }
+            ),
+            (
+                DiagnosticSeverity::Warning,
+                DiagnosticKind::DuplicateTemplateParameter,
+                Span { start: 8, end: 13 }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_recovering_simplification() {
+    let recover = |wikitext: &str| {
+        let (simplified, diagnostics) = parse_and_simplify_wikitext_recovering(
+            wikitext,
+            &PWT_CONFIGURATION,
+            &SimplificationConfig::default(),
+        );
+        let diagnostics = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.kind.code(), d.span))
+            .collect::<Vec<_>>();
+        (simplified, diagnostics)
+    };
+
+    // `'''''` closing italics outside of bold closes the italics and opens bold, which is
+    // left open at the end of the line
+    let wikitext = "''a'''''b";
+    assert_eq!(
+        recover(wikitext),
+        (
+            vec![
+                sp(
+                    WSN::Italic {
+                        children: vec![sp(WSN::Text { text: "a".into() }, 2, 3)]
+                    },
+                    0,
+                    8
+                ),
+                sp(
+                    WSN::Bold {
+                        children: vec![sp(WSN::Text { text: "b".into() }, 8, 9)]
+                    },
+                    3,
+                    9
+                ),
+            ],
+            vec![(
+                DiagnosticSeverity::Warning,
+                "unclosed-formatting",
+                Span { start: 3, end: 9 }
+            )]
+        )
+    );
+
+    // Italics opened within bold that are still open when the bold is closed are closed
+    // with it and reopened after it
+    let wikitext = "'''a''b'''c''";
+    assert_eq!(
+        recover(wikitext),
+        (
+            vec![
+                sp(
+                    WSN::Bold {
+                        children: vec![
+                            sp(WSN::Text { text: "a".into() }, 3, 4),
+                            sp(
+                                WSN::Italic {
+                                    children: vec![sp(WSN::Text { text: "b".into() }, 6, 7)]
+                                },
+                                4,
+                                10
+                            ),
+                        ]
+                    },
+                    0,
+                    10
+                ),
+                sp(
+                    WSN::Italic {
+                        children: vec![sp(WSN::Text { text: "c".into() }, 10, 11)]
+                    },
+                    7,
+                    13
+                ),
+            ],
+            vec![(
+                DiagnosticSeverity::Warning,
+                "misnested-formatting",
+                Span { start: 7, end: 10 }
+            )]
+        )
+    );
+
+    // Closing tags close the tags opened within them, and unmatched closing tags are discarded
+    assert_eq!(
+        recover("<div><span>x</div>y</sup>"),
+        (
+            vec![
+                sp(
+                    WSN::Tag {
+                        name: "div".into(),
+                        attributes: vec![],
+                        raw_attributes: None,
+                        children: vec![sp(
+                            WSN::Tag {
+                                name: "span".into(),
+                                attributes: vec![],
+                                raw_attributes: None,
+                                children: vec![sp(WSN::Text { text: "x".into() }, 11, 12)]
+                            },
+                            5,
+                            12
+                        )]
+                    },
+                    0,
+                    18
+                ),
+                sp(WSN::Text { text: "y".into() }, 18, 19),
+            ],
+            vec![
+                (
+                    DiagnosticSeverity::Warning,
+                    "unclosed-tag",
+                    Span { start: 5, end: 12 }
+                ),
+                (
+                    DiagnosticSeverity::Warning,
+                    "unmatched-closing-tag",
+                    Span { start: 19, end: 25 }
+                ),
+            ]
+        )
+    );
+
+    // Nodes that cannot be simplified are kept as text
+    let wikitext = "a</br>b";
+    assert!(parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).is_err());
+    assert_eq!(
+        recover(wikitext),
+        (
+            vec![
+                sp(WSN::Text { text: "a".into() }, 0, 1),
+                sp(
+                    WSN::Text {
+                        text: "</br>".into()
+                    },
+                    1,
+                    6
+                ),
+                sp(WSN::Text { text: "b".into() }, 6, 7),
+            ],
+            vec![(
+                DiagnosticSeverity::Error,
+                "unknown-node",
+                Span { start: 1, end: 6 }
+            )]
+        )
+    );
+
+    // Well-formed wikitext is simplified as usual, without diagnostics
+    let wikitext = "'''a''' <sup>b</sup> {{t|<div>c}}";
+    assert_eq!(
+        recover(wikitext),
+        (
+            parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap(),
+            vec![]
+        )
+    );
+}
+
+#[test]
+fn test_to_wikitext_template_explicit_numbering() {
+    let param = |name: &str, value: &str| TemplateParameter {
+        name: name.into(),
+        value: vec![sp(WSN::Text { text: value.into() }, 0, 0)],
+        raw_value: value.into(),
+        span: Span { start: 0, end: 0 },
+        name_span: None,
+        value_span: Span { start: 0, end: 0 },
+    };
+
+    // Parameters that are out of sequence or contain `=` must stay numbered
+    let node = WSN::Template {
+        name: "Template".into(),
+        normalized_name: TemplateName::parse("Template"),
+        name_span: Span { start: 0, end: 0 },
+        parameters: vec![param("1", "a"), param("3", "c"), param("2", "x=y")],
+    };
+    assert_eq!(node.to_wikitext(), "{{Template|a|3=c|2=x=y}}");
+}
+
+#[test]
+fn test_template_ref() {
+    let param = |name: &str, value: &str| TemplateParameter {
+        name: name.into(),
+        value: vec![sp(WSN::Text { text: value.into() }, 0, 0)],
+        raw_value: value.into(),
+        span: Span { start: 0, end: 0 },
+        name_span: None,
+        value_span: Span { start: 0, end: 0 },
+    };
+    let node = WSN::Template {
+        name: "Infobox person".into(),
+        normalized_name: TemplateName::parse("Infobox person"),
+        name_span: Span { start: 2, end: 16 },
+        parameters: vec![
+            param("3", "third"),
+            param("1", "first"),
+            param("Birth date", "1970"),
+            param("image_name", "Example.jpg"),
+        ],
+    };
+
+    let template = node.as_template().unwrap();
+    assert_eq!(template.name(), "Infobox person");
+    assert_eq!(template.raw_value(" birth  DATE "), Some("1970"));
+    assert_eq!(
+        template.value("birth date"),
+        Some(
+            [sp(
+                WSN::Text {
+                    text: "1970".into()
+                },
+                0,
+                0
+            )]
+            .as_slice()
+        )
+    );
+    assert_eq!(template.get("death date"), None);
+    assert_eq!(
+        template
+            .get_any(&["image", "image_name"])
+            .map(|p| p.raw_value.as_str()),
+        Some("Example.jpg")
+    );
+    assert_eq!(
+        template.get_positional(1).map(|p| p.raw_value.as_str()),
+        Some("first")
+    );
+    assert_eq!(template.get_positional(2), None);
+    assert_eq!(
+        template
+            .positional()
+            .map(|p| p.raw_value.as_str())
+            .collect::<Vec<_>>(),
+        vec!["first", "third"]
+    );
+
+    assert!(WSN::Text { text: "".into() }.as_template().is_none());
+}
+
+#[test]
+fn test_template_name_normalization() {
+    let name = |title: &str, namespace: &str, modifiers: Vec<TemplateModifier>| TemplateName {
+        title: title.into(),
+        namespace: namespace.into(),
+        modifiers,
+    };
+
+    assert_eq!(
+        TemplateName::parse(" infobox_person "),
+        name("Infobox person", "Template", vec![])
+    );
+    assert_eq!(
+        TemplateName::parse("Infobox  person"),
+        name("Infobox person", "Template", vec![])
+    );
+    assert_eq!(
+        TemplateName::parse("template:Infobox person"),
+        name("Infobox person", "Template", vec![])
+    );
+    assert_eq!(
+        TemplateName::parse(":Main Page"),
+        name("Main Page", "", vec![])
+    );
+    assert_eq!(
+        TemplateName::parse("User_talk:example/sig"),
+        name("Example/sig", "User talk", vec![])
+    );
+    assert_eq!(
+        TemplateName::parse("SUBST: msgnl:cite web"),
+        name(
+            "Cite web",
+            "Template",
+            vec![TemplateModifier::Subst, TemplateModifier::Msgnl]
+        )
+    );
+    assert_eq!(
+        TemplateName::parse("safesubst::Foo"),
+        name("Foo", "", vec![TemplateModifier::SafeSubst])
+    );
+    // Namespace aliases resolve to the canonical namespace
+    assert_eq!(
+        TemplateName::parse("WP:Manual of Style"),
+        name("Manual of Style", "Wikipedia", vec![])
+    );
+    // Unknown prefixes are part of the title
+    assert_eq!(
+        TemplateName::parse("foo:bar"),
+        name("Foo:bar", "Template", vec![])
+    );
+
+    assert_eq!(
+        TemplateName::parse("Template:Infobox person").full_title(),
+        "Template:Infobox person"
+    );
+    assert_eq!(TemplateName::parse(":Main Page").full_title(), "Main Page");
+}
+
+#[test]
+fn test_link_targets() {
+    let target = |leading_colon: bool,
+                  interwiki: Option<&str>,
+                  interlanguage: bool,
+                  namespace: &str,
+                  title: &str,
+                  fragment: Option<&str>| LinkTarget {
+        leading_colon,
+        interwiki: interwiki.map(|s| s.into()),
+        interlanguage,
+        namespace: namespace.into(),
+        title: title.into(),
+        fragment: fragment.map(|s| s.into()),
+    };
+
+    assert_eq!(
+        LinkTarget::parse("main_page"),
+        target(false, None, false, "", "Main page", None)
+    );
+    assert_eq!(
+        LinkTarget::parse("Help:Contents#Editing"),
+        target(false, None, false, "Help", "Contents", Some("Editing"))
+    );
+    assert_eq!(
+        LinkTarget::parse("#Section"),
+        target(false, None, false, "", "", Some("Section"))
+    );
+    assert!(LinkTarget::parse("#Section").is_fragment_only());
+    assert_eq!(
+        LinkTarget::parse(":Category:Foo"),
+        target(true, None, false, "Category", "Foo", None)
+    );
+    assert_eq!(
+        LinkTarget::parse("image:Example.jpg"),
+        target(false, None, false, "File", "Example.jpg", None)
+    );
+    assert_eq!(
+        LinkTarget::parse(":fr:Paris"),
+        target(true, Some("fr"), false, "", "Paris", None)
+    );
+    assert_eq!(
+        LinkTarget::parse("fr:Paris"),
+        target(false, Some("fr"), true, "", "Paris", None)
+    );
+    assert_eq!(
+        LinkTarget::parse("wikt:fr:chat#French"),
+        target(false, Some("wikt"), false, "", "fr:chat", Some("French"))
+    );
+    assert_eq!(
+        LinkTarget::parse("Unknown:Page"),
+        target(false, None, false, "", "Unknown:Page", None)
+    );
+
+    assert_eq!(
+        LinkTarget::parse("wikt:fr:chat").full_title(),
+        "wikt:fr:chat"
+    );
+    assert_eq!(
+        LinkTarget::parse("Help:Contents#Editing").full_title(),
+        "Help:Contents"
+    );
+
+    let prefixes = wikitext_util::LinkPrefixes {
+        namespaces: vec!["Help".into()],
+        namespace_aliases: vec![],
+        interwiki: vec!["foo".into()],
+        languages: vec![],
+        protocols: vec![],
+    };
+    assert_eq!(
+        LinkTarget::parse_with_prefixes("Foo:Bar", &prefixes),
+        target(false, Some("foo"), false, "", "Bar", None)
+    );
+    assert_eq!(
+        LinkTarget::parse_with_prefixes("Category:Bar", &prefixes),
+        target(false, None, false, "", "Category:Bar", None)
+    );
+    assert_eq!(
+        TemplateName::parse_with_prefixes("Help:Foo", &prefixes).full_title(),
+        "Help:Foo"
+    );
+    assert_eq!(
+        TemplateName::parse_with_prefixes("User:Foo", &prefixes).full_title(),
+        "Template:User:Foo"
+    );
+}
+
+#[test]
+fn test_formatted_link_text() {
+    let wikitext = "[[Foo|''bar'' baz]]s";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Link {
+                text: vec![
+                    sp(
+                        WSN::Italic {
+                            children: vec![sp(WSN::Text { text: "bar".into() }, 8, 11)]
+                        },
+                        6,
+                        13
+                    ),
+                    sp(
+                        WSN::Text {
+                            text: " baz".into()
+                        },
+                        13,
+                        17
+                    ),
+                    sp(WSN::Text { text: "s".into() }, 19, 20),
+                ],
+                title: "Foo".into(),
+                target: LinkTarget::parse("Foo"),
+            },
+            0,
+            20
+        )]
+    );
+    assert_eq!(simplified[0].value.to_plain_text(), "bar bazs");
+    assert_eq!(simplified[0].value.to_wikitext(), "[[Foo|''bar'' bazs]]");
+}
+
+#[test]
+fn test_to_plain_text() {
+    let wikitext = "'''Hello''' [[World|world]]<ref>Note</ref>{{Template}}<!-- comment -->!";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        WSN::Fragment {
+            children: simplified
+        }
+        .to_plain_text(),
+        "Hello world!"
+    );
+}
+
+#[test]
+fn test_external_link_with_template_and_formatted_text() {
+    let wikitext = "[https://example.com/{{X|a b}}\t''label'' text]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    let [Spanned {
+        value:
+            WSN::ExtLink {
+                link,
+                protocol,
+                text: Some(text),
+                bracketed,
+            },
+        span,
+    }] = simplified.as_slice()
+    else {
+        panic!("expected a single external link, got {simplified:?}");
+    };
+    assert_eq!(link, "https://example.com/{{X|a b}}");
+    assert_eq!(protocol, "https://");
+    assert!(*bracketed);
+    assert_eq!(*span, Span { start: 0, end: 46 });
+    assert_eq!(
+        text,
+        &vec![
+            sp(
+                WSN::Italic {
+                    children: vec![sp(
+                        WSN::Text {
+                            text: "label".into()
+                        },
+                        33,
+                        38
+                    )]
+                },
+                31,
+                40
+            ),
+            sp(
+                WSN::Text {
+                    text: " text".into()
+                },
+                40,
+                45
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_protocol_relative_external_link() {
+    let wikitext = "[//example.com]";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::ExtLink {
+                link: "//example.com".into(),
+                protocol: "//".into(),
+                text: None,
+                bracketed: true,
+            },
+            0,
+            15
+        )]
+    );
+}
+
+#[test]
+fn test_bare_url_autolinks() {
+    let bare = |link: &str, protocol: &str, start: usize| {
+        sp(
+            WSN::ExtLink {
+                link: link.into(),
+                protocol: protocol.into(),
+                text: None,
+                bracketed: false,
+            },
+            start,
+            start + link.len(),
+        )
+    };
+
+    let wikitext = "See https://example.com/a_(b), or (HTTP://example.org/c). mailto:a@b.c!";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![
+            sp(
+                WSN::Text {
+                    text: "See ".into()
+                },
+                0,
+                4
+            ),
+            bare("https://example.com/a_(b)", "https://", 4),
+            sp(
+                WSN::Text {
+                    text: ", or (".into()
+                },
+                29,
+                35
+            ),
+            bare("HTTP://example.org/c", "HTTP://", 35),
+            sp(WSN::Text { text: "). ".into() }, 55, 58),
+            bare("mailto:a@b.c", "mailto:", 58),
+            sp(WSN::Text { text: "!".into() }, 70, 71),
+        ]
+    );
+
+    // URLs must start at a word boundary, must have something after the protocol, and
+    // are not linked if protocol-relative
+    let wikitext = "xhttp://example.com http:// //example.com";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(
+        simplified,
+        vec![sp(
+            WSN::Text {
+                text: wikitext.into()
+            },
+            0,
+            41
+        )]
+    );
+
+    // URLs are not linked within other links or `<pre>` tags, but are within other markup
+    let wikitext = "[[Foo|http://a.com]] <pre>http://b.com</pre> '''http://c.com'''";
+    let simplified = parse_and_simplify_wikitext(wikitext, &PWT_CONFIGURATION).unwrap();
+    assert_eq!(simplified[0].value.to_wikitext(), "[[Foo|http://a.com]]");
+    assert!(matches!(
+        simplified[0].value,
+        WSN::Link { ref text, .. } if matches!(text[..], [Spanned { value: WSN::Text { .. }, .. }])
+    ));
+    assert!(matches!(
+        &simplified[2].value,
+        WSN::Preformatted { children } if matches!(children[..], [Spanned { value: WSN::Text { .. }, .. }])
+    ));
+    assert_eq!(
+        simplified[4],
+        sp(
+            WSN::Bold {
+                children: vec![bare("http://c.com", "http://", 48)]
+            },
+            45,
+            63
+        )
+    );
+}
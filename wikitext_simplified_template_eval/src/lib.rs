@@ -216,6 +216,7 @@ impl<'a> TemplateEvaluator<'a> {
             WSN::Template {
                 name,
                 parameters: template_params,
+                ..
             } => {
                 template_calls.push((name.clone(), template_params.clone()));
                 // Placeholder - will be replaced
@@ -224,13 +225,12 @@ impl<'a> TemplateEvaluator<'a> {
                 }
             }
             WSN::TemplateParameterUse { name, default } => {
-                let parameter = parameters
-                    .iter()
-                    .find(|p| p.name == *name)
-                    .map(|p| p.value.clone())
-                    .or_else(|| self.context.resolve_magic_variable(name));
-                if let Some(parameter) = parameter {
-                    WSN::Text { text: parameter }
+                if let Some(parameter) = parameters.iter().find(|p| p.name == *name) {
+                    WSN::Fragment {
+                        children: parameter.value.clone(),
+                    }
+                } else if let Some(value) = self.context.resolve_magic_variable(name) {
+                    WSN::Text { text: value }
                 } else if let Some(default) = default {
                     WSN::Text {
                         text: WSN::Fragment {
@@ -247,9 +247,17 @@ impl<'a> TemplateEvaluator<'a> {
             _ => node.clone(),
         });
 
-        // Second pass: instantiate templates and collect results
-        let mut results = Vec::new();
-        for (name, params) in template_calls {
+        // Second pass: instantiate templates and collect results. Templates within the
+        // parameters of a template are visited before it, so their placeholders can be
+        // replaced with their results before it is instantiated.
+        let mut results: Vec<WSN> = Vec::new();
+        for (name, mut params) in template_calls {
+            for param in &mut params {
+                for node in &mut param.value {
+                    node.value
+                        .visit_and_replace_mut(&mut |node| replace_placeholder(node, &results));
+                }
+            }
             let result =
                 Box::pin(self.instantiate(TemplateToInstantiate::Name(&name), &params)).await;
             // Flatten single-child fragments
@@ -263,17 +271,7 @@ impl<'a> TemplateEvaluator<'a> {
         }
 
         // Third pass: replace placeholders with actual results
-        for (idx, result) in results.into_iter().enumerate() {
-            let placeholder = format!("__TEMPLATE_PLACEHOLDER_{idx}__");
-            template.visit_and_replace_mut(&mut |node| {
-                if let WSN::Text { text } = node
-                    && text == &placeholder
-                {
-                    return result.clone();
-                }
-                node.clone()
-            });
-        }
+        template.visit_and_replace_mut(&mut |node| replace_placeholder(node, &results));
     }
 
     /// Instantiate a template by replacing all template parameter uses with their values,
@@ -368,3 +366,21 @@ impl<'a> TemplateEvaluator<'a> {
         }
     }
 }
+
+/// Returns the result of the template call that the node is a placeholder for, or a copy of
+/// the node if it is not a placeholder for one of the results.
+fn replace_placeholder(
+    node: &WikitextSimplifiedNode,
+    results: &[WikitextSimplifiedNode],
+) -> WikitextSimplifiedNode {
+    if let WikitextSimplifiedNode::Text { text } = node
+        && let Some(result) = text
+            .strip_prefix("__TEMPLATE_PLACEHOLDER_")
+            .and_then(|rest| rest.strip_suffix("__"))
+            .and_then(|idx| idx.parse::<usize>().ok())
+            .and_then(|idx| results.get(idx))
+    {
+        return result.clone();
+    }
+    node.clone()
+}
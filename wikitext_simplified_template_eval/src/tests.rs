@@ -5,7 +5,7 @@ use parse_wiki_text_2::Configuration;
 use crate::{
     TemplateContext, TemplateError, TemplateEvaluator, TemplateToInstantiate, async_trait,
 };
-use wikitext_simplified::WikitextSimplifiedNode;
+use wikitext_simplified::{WikitextSimplifiedNode, collect_categories};
 
 /// In-memory template loader for testing.
 struct MockContext {
@@ -217,3 +217,75 @@ fn test_magic_variable_resolution() {
         "Magic variable should be resolved: {text}"
     );
 }
+
+#[test]
+fn test_categories_from_templates_are_collected() {
+    let mut context = MockContext::new();
+    context.add_template(
+        "stub",
+        "''This article is a stub.''[[Category:Stubs|Example]]",
+    );
+    context.add_template("page", "Some text.{{stub}}[[Category:Examples]]");
+
+    let mut evaluator = TemplateEvaluator::new(&context);
+    let result = block_on(evaluator.instantiate(TemplateToInstantiate::Name("page"), &[]));
+
+    let WikitextSimplifiedNode::Fragment { children } = &result else {
+        panic!("Expected Fragment, got {result:?}");
+    };
+    let categories = collect_categories(children)
+        .into_iter()
+        .map(|c| (c.value.name, c.value.sort_key))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        categories,
+        vec![
+            ("Stubs".to_string(), Some("Example".to_string())),
+            ("Examples".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn test_parameter_values_keep_their_structure() {
+    let mut context = MockContext::new();
+    context.add_template("greet", "Hello, {{{name}}}!");
+    context.add_template("page", "{{greet|name=[[Alice]]}}");
+
+    let mut evaluator = TemplateEvaluator::new(&context);
+    let result = block_on(evaluator.instantiate(TemplateToInstantiate::Name("page"), &[]));
+
+    let mut found_link = false;
+    result.visit(&mut |node| {
+        found_link |=
+            matches!(node, WikitextSimplifiedNode::Link { title, .. } if title == "Alice");
+    });
+    assert!(
+        found_link,
+        "Parameter value should contain a link: {result:?}"
+    );
+    assert_eq!(result.to_wikitext(), "Hello, [[Alice]]!");
+}
+
+#[test]
+fn test_explicit_numbered_parameter_overrides_positional() {
+    let mut context = MockContext::new();
+    context.add_template("greet", "Hello, {{{1}}} and {{{name}}}!");
+    context.add_template("page", "{{greet|Alice|name=Bob|1=Carol| name = Dave }}");
+
+    let mut evaluator = TemplateEvaluator::new(&context);
+    let result = block_on(evaluator.instantiate(TemplateToInstantiate::Name("page"), &[]));
+    assert_eq!(result.to_wikitext(), "Hello, Carol and Dave!");
+}
+
+#[test]
+fn test_templates_in_parameters_are_instantiated() {
+    let mut context = MockContext::new();
+    context.add_template("greet", "Hello, {{{1}}}!");
+    context.add_template("name", "Alice");
+    context.add_template("page", "{{greet|{{name}}}}");
+
+    let mut evaluator = TemplateEvaluator::new(&context);
+    let result = block_on(evaluator.instantiate(TemplateToInstantiate::Name("page"), &[]));
+    assert_eq!(result.to_wikitext(), "Hello, Alice!");
+}
@@ -6,6 +6,7 @@
 #![deny(missing_docs)]
 pub use parse_wiki_text_2;
 use parse_wiki_text_2 as pwt;
+use std::sync::LazyLock;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// The type of a node in the wikitext, as noted by [`NodeMetadata::name`].
@@ -240,6 +241,212 @@ pub fn node_inner_text(node: &pwt::Node, config: InnerTextConfig) -> String {
     }
 }
 
+/// Normalizes a template parameter name for comparison, so that lookups are insensitive
+/// to case and whitespace (e.g. ` Birth  Date ` becomes `birth date`).
+pub fn normalize_parameter_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Normalizes a page title as MediaWiki does: underscores become spaces, runs of whitespace
+/// are collapsed, and the first letter is uppercased (e.g. ` infobox_person ` becomes
+/// `Infobox person`).
+pub fn normalize_page_title(title: &str) -> String {
+    let title = title.replace('_', " ");
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => title,
+    }
+}
+
+/// The prefixes that can appear at the start of a link target or template name.
+///
+/// Unlike the rest of the syntax, these differ between wikis; see
+/// [`wikipedia_link_prefixes`] for the prefixes used by Wikipedia.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPrefixes {
+    /// The canonical names of the namespaces, excluding the main namespace (e.g. `User talk`)
+    pub namespaces: Vec<String>,
+    /// Alternative names for namespaces, mapped to their canonical names (e.g. `Image` to `File`)
+    pub namespace_aliases: Vec<(String, String)>,
+    /// The prefixes of links to other wikis, in lowercase (e.g. `wikt`)
+    pub interwiki: Vec<String>,
+    /// The prefixes of links to other languages of the same wiki, in lowercase (e.g. `fr`)
+    pub languages: Vec<String>,
+    /// The protocols that can start an external link, in lowercase (e.g. `https://`).
+    ///
+    /// These should match the protocols given to the parser.
+    pub protocols: Vec<String>,
+}
+impl LinkPrefixes {
+    /// Returns the canonical name of the namespace with the given name or alias, ignoring
+    /// case, underscores and whitespace, or `None` if it is not a namespace.
+    pub fn namespace(&self, name: &str) -> Option<&str> {
+        let name = normalize_page_title(name);
+        self.namespaces
+            .iter()
+            .find(|namespace| namespace.eq_ignore_ascii_case(&name))
+            .or_else(|| {
+                self.namespace_aliases
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(&name))
+                    .map(|(_, namespace)| namespace)
+            })
+            .map(|namespace| namespace.as_str())
+    }
+
+    /// Returns whether the given prefix links to another wiki, including other languages,
+    /// ignoring case and surrounding whitespace.
+    pub fn is_interwiki(&self, prefix: &str) -> bool {
+        let prefix = prefix.trim().to_lowercase();
+        self.interwiki.contains(&prefix) || self.languages.contains(&prefix)
+    }
+
+    /// Returns the longest protocol that the given URL starts with, ignoring case.
+    pub fn protocol<'a>(&self, url: &'a str) -> Option<&'a str> {
+        self.protocols
+            .iter()
+            .filter(|protocol| {
+                url.get(..protocol.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(protocol))
+            })
+            .map(|protocol| &url[..protocol.len()])
+            .max_by_key(|protocol| protocol.len())
+    }
+
+    /// Returns whether the given prefix links to another language of the same wiki,
+    /// ignoring case and surrounding whitespace.
+    pub fn is_language(&self, prefix: &str) -> bool {
+        self.languages.contains(&prefix.trim().to_lowercase())
+    }
+}
+
+/// A view over a [`pwt::Node::Template`] for looking up its parameters by name or position.
+///
+/// Parameter names are resolved as MediaWiki does: positional parameters are numbered from 1,
+/// explicitly numbered parameters (e.g. `|2=`) share those numbers, and later parameters
+/// override earlier ones with the same name.
+///
+/// This is the raw counterpart of `wikitext_simplified`'s `TemplateRef`, for code that works
+/// with the nodes from [`parse_wiki_text_2`] directly.
+#[derive(Debug, Clone, Copy)]
+pub struct RawTemplateRef<'a, 'b> {
+    name: &'b [pwt::Node<'a>],
+    parameters: &'b [pwt::Parameter<'a>],
+}
+impl<'a, 'b> RawTemplateRef<'a, 'b> {
+    /// Creates a view over the given node, or returns `None` if it is not a template.
+    pub fn new(node: &'b pwt::Node<'a>) -> Option<Self> {
+        match node {
+            pwt::Node::Template {
+                name, parameters, ..
+            } => Some(Self { name, parameters }),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the template as text.
+    pub fn name(&self) -> String {
+        nodes_inner_text(self.name)
+    }
+
+    /// Returns the nodes that make up the name of the template.
+    pub fn name_nodes(&self) -> &'b [pwt::Node<'a>] {
+        self.name
+    }
+
+    /// Returns all parameters in order of appearance, together with their resolved names.
+    ///
+    /// This includes parameters that are overridden by later parameters with the same name.
+    pub fn parameters(&self) -> impl Iterator<Item = (String, &'b pwt::Parameter<'a>)> {
+        let mut positional_index = 0;
+        self.parameters
+            .iter()
+            .map(move |parameter| match &parameter.name {
+                Some(name) => (nodes_inner_text(name), parameter),
+                None => {
+                    positional_index += 1;
+                    (positional_index.to_string(), parameter)
+                }
+            })
+    }
+
+    /// Returns the parameter with the given name, ignoring case and whitespace.
+    pub fn get(&self, name: &str) -> Option<&'b pwt::Parameter<'a>> {
+        let name = normalize_parameter_name(name);
+        self.parameters()
+            .filter(|(n, _)| normalize_parameter_name(n) == name)
+            .last()
+            .map(|(_, parameter)| parameter)
+    }
+
+    /// Returns the first parameter found from the given names, which is useful for
+    /// parameters with aliases (e.g. `image`, then `image_name`).
+    pub fn get_any(&self, names: &[&str]) -> Option<&'b pwt::Parameter<'a>> {
+        names.iter().find_map(|name| self.get(name))
+    }
+
+    /// Returns the positional parameter at the given index, starting from 1.
+    pub fn get_positional(&self, index: usize) -> Option<&'b pwt::Parameter<'a>> {
+        self.get(&index.to_string())
+    }
+
+    /// Returns the positional parameters in order of their index, including explicitly
+    /// numbered parameters.
+    pub fn positional(&self) -> impl Iterator<Item = &'b pwt::Parameter<'a>> {
+        let mut positional = self
+            .parameters()
+            .filter_map(|(name, parameter)| Some((name.trim().parse::<usize>().ok()?, parameter)))
+            .collect::<Vec<_>>();
+        // Keep the last parameter for each index
+        positional.reverse();
+        positional.sort_by_key(|(index, _)| *index);
+        positional.dedup_by_key(|(index, _)| *index);
+        positional.into_iter().map(|(_, parameter)| parameter)
+    }
+
+    /// Returns the value nodes of the parameter with the given name, ignoring case and whitespace.
+    pub fn value(&self, name: &str) -> Option<&'b [pwt::Node<'a>]> {
+        self.get(name).map(|parameter| parameter.value.as_slice())
+    }
+}
+
+/// The protocols that can be used for external links on Wikipedia.
+const WIKIPEDIA_PROTOCOLS: &[&str] = &[
+    "//",
+    "bitcoin:",
+    "ftp://",
+    "ftps://",
+    "geo:",
+    "git://",
+    "gopher://",
+    "http://",
+    "https://",
+    "irc://",
+    "ircs://",
+    "magnet:",
+    "mailto:",
+    "mms://",
+    "news:",
+    "nntp://",
+    "redis://",
+    "sftp://",
+    "sip:",
+    "sips:",
+    "sms:",
+    "ssh://",
+    "svn://",
+    "tel:",
+    "telnet://",
+    "urn:",
+    "worldwind://",
+    "xmpp:",
+];
+
 /// Creates a Wikipedia-compatible configuration for the `parse_wiki_text_2` parser.
 ///
 /// This configuration includes Wikipedia-specific settings for:
@@ -303,36 +510,170 @@ pub fn wikipedia_pwt_configuration() -> pwt::Configuration {
             "staticredirect",
             "toc",
         ],
-        protocols: &[
-            "//",
-            "bitcoin:",
-            "ftp://",
-            "ftps://",
-            "geo:",
-            "git://",
-            "gopher://",
-            "http://",
-            "https://",
-            "irc://",
-            "ircs://",
-            "magnet:",
-            "mailto:",
-            "mms://",
-            "news:",
-            "nntp://",
-            "redis://",
-            "sftp://",
-            "sip:",
-            "sips:",
-            "sms:",
-            "ssh://",
-            "svn://",
-            "tel:",
-            "telnet://",
-            "urn:",
-            "worldwind://",
-            "xmpp:",
-        ],
+        protocols: WIKIPEDIA_PROTOCOLS,
         redirect_magic_words: &["redirect"],
     })
 }
+
+/// Returns the [`LinkPrefixes`] used by the English Wikipedia.
+///
+/// They are only built once, on first use. The list of languages only includes the larger
+/// Wikipedias.
+pub fn wikipedia_link_prefixes() -> &'static LinkPrefixes {
+    static PREFIXES: LazyLock<LinkPrefixes> = LazyLock::new(|| {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        LinkPrefixes {
+            namespaces: strings(&[
+                "Talk",
+                "User",
+                "User talk",
+                "Wikipedia",
+                "Wikipedia talk",
+                "File",
+                "File talk",
+                "MediaWiki",
+                "MediaWiki talk",
+                "Template",
+                "Template talk",
+                "Help",
+                "Help talk",
+                "Category",
+                "Category talk",
+                "Portal",
+                "Portal talk",
+                "Draft",
+                "Draft talk",
+                "TimedText",
+                "TimedText talk",
+                "Module",
+                "Module talk",
+                "Special",
+                "Media",
+            ]),
+            namespace_aliases: [
+                ("WP", "Wikipedia"),
+                ("WT", "Wikipedia talk"),
+                ("Project", "Wikipedia"),
+                ("Project talk", "Wikipedia talk"),
+                ("Image", "File"),
+                ("Image talk", "File talk"),
+                ("CAT", "Category"),
+                ("H", "Help"),
+                ("MOS", "Wikipedia"),
+                ("P", "Portal"),
+                ("T", "Template"),
+            ]
+            .iter()
+            .map(|(alias, namespace)| (alias.to_string(), namespace.to_string()))
+            .collect(),
+            interwiki: strings(&[
+                "b",
+                "c",
+                "commons",
+                "d",
+                "foundation",
+                "m",
+                "meta",
+                "mw",
+                "n",
+                "phab",
+                "q",
+                "s",
+                "species",
+                "v",
+                "voy",
+                "w",
+                "wikibooks",
+                "wikidata",
+                "wikimedia",
+                "wikinews",
+                "wikipedia",
+                "wikiquote",
+                "wikisource",
+                "wikispecies",
+                "wikiversity",
+                "wikivoyage",
+                "wikt",
+                "wiktionary",
+                "wmf",
+            ]),
+            languages: strings(&[
+                "af",
+                "ar",
+                "arz",
+                "ast",
+                "az",
+                "be",
+                "bg",
+                "bn",
+                "ca",
+                "ce",
+                "ceb",
+                "cs",
+                "cy",
+                "da",
+                "de",
+                "el",
+                "en",
+                "eo",
+                "es",
+                "et",
+                "eu",
+                "fa",
+                "fi",
+                "fr",
+                "ga",
+                "gl",
+                "he",
+                "hi",
+                "hr",
+                "hu",
+                "hy",
+                "id",
+                "it",
+                "ja",
+                "ka",
+                "kk",
+                "ko",
+                "la",
+                "lt",
+                "lv",
+                "min",
+                "mk",
+                "ms",
+                "my",
+                "nl",
+                "nn",
+                "no",
+                "pl",
+                "pt",
+                "ro",
+                "ru",
+                "sh",
+                "simple",
+                "sk",
+                "sl",
+                "sr",
+                "sv",
+                "ta",
+                "tg",
+                "th",
+                "tr",
+                "tt",
+                "uk",
+                "ur",
+                "uz",
+                "vi",
+                "war",
+                "zh",
+                "zh-min-nan",
+                "zh-yue",
+            ]),
+            protocols: strings(WIKIPEDIA_PROTOCOLS),
+        }
+    });
+    &PREFIXES
+}
+
+#[cfg(test)]
+mod tests;
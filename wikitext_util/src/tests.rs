@@ -0,0 +1,88 @@
+use super::*;
+
+use std::sync::LazyLock;
+
+static PWT_CONFIGURATION: LazyLock<pwt::Configuration> = LazyLock::new(wikipedia_pwt_configuration);
+
+// Helper function to parse wikitext that consists of a single template
+fn parse_template(wikitext: &str) -> pwt::Output<'_> {
+    let output = PWT_CONFIGURATION.parse(wikitext).unwrap();
+    assert_eq!(output.nodes.len(), 1, "expected a single node: {output:?}");
+    output
+}
+
+// Helper function to get the text of a parameter's value
+fn value_text(parameter: &pwt::Parameter) -> String {
+    nodes_inner_text(&parameter.value)
+}
+
+#[test]
+fn test_raw_template_ref_name() {
+    let output = parse_template("{{Infobox person|name=Foo}}");
+    let template = RawTemplateRef::new(&output.nodes[0]).unwrap();
+    assert_eq!(template.name(), "Infobox person");
+    assert_eq!(template.name_nodes().len(), 1);
+
+    let output = PWT_CONFIGURATION.parse("Not a template").unwrap();
+    assert!(RawTemplateRef::new(&output.nodes[0]).is_none());
+}
+
+#[test]
+fn test_raw_template_ref_parameter_names() {
+    // Positional parameters are numbered from 1, skipping named parameters
+    let output = parse_template("{{t|a|name=b|c|2=d}}");
+    let template = RawTemplateRef::new(&output.nodes[0]).unwrap();
+    assert_eq!(
+        template
+            .parameters()
+            .map(|(name, parameter)| (name, value_text(parameter)))
+            .collect::<Vec<_>>(),
+        vec![
+            ("1".to_string(), "a".to_string()),
+            ("name".to_string(), "b".to_string()),
+            ("2".to_string(), "c".to_string()),
+            ("2".to_string(), "d".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_raw_template_ref_get() {
+    // Later parameters override earlier ones, ignoring case and whitespace in names
+    let output = parse_template("{{t|Name=a| name =b|1=c|d}}");
+    let template = RawTemplateRef::new(&output.nodes[0]).unwrap();
+    assert_eq!(template.get("name").map(value_text).as_deref(), Some("b"));
+    assert_eq!(template.get("NAME").map(value_text).as_deref(), Some("b"));
+    assert_eq!(
+        template.value("name").map(nodes_inner_text).as_deref(),
+        Some("b")
+    );
+    assert!(template.get("missing").is_none());
+
+    // A positional parameter after an explicit `1=` overrides it
+    assert_eq!(
+        template.get_positional(1).map(value_text).as_deref(),
+        Some("d")
+    );
+
+    assert_eq!(
+        template
+            .get_any(&["image", "name"])
+            .map(value_text)
+            .as_deref(),
+        Some("b")
+    );
+    assert!(template.get_any(&["image", "image_name"]).is_none());
+}
+
+#[test]
+fn test_raw_template_ref_positional() {
+    // Explicitly numbered parameters share the positional numbers, and each index is only
+    // returned once, for its last parameter
+    let output = parse_template("{{t|3=e|a|1=b|c|name=x}}");
+    let template = RawTemplateRef::new(&output.nodes[0]).unwrap();
+    assert_eq!(
+        template.positional().map(value_text).collect::<Vec<_>>(),
+        vec!["b", "c", "e"]
+    );
+}